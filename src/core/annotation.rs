@@ -0,0 +1,118 @@
+use serde::{Deserialize, Serialize};
+
+use crate::core::cell::Point;
+
+// Player drawn markings. They sit on top of the puzzle and never take part in
+// checking or solving it.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Annotations {
+    pub lines: Vec<Line>,
+    // Free text about the puzzle as a whole, cell notes live on the cells
+    #[serde(default)]
+    pub notes: String,
+}
+
+// Points are measured in half cells from the top left corner of the board, so
+// cell centers have odd coordinates and cell corners even ones. A line keeps
+// to the kind of point it started on.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Line {
+    pub points: Vec<Point>,
+    pub color: u8,
+}
+
+impl Line {
+    pub fn new(start: Point, color: u8) -> Line {
+        Line {
+            points: vec![start],
+            color,
+        }
+    }
+
+    // Walks from the last point towards `target` one step at a time, so fast
+    // drags don't skip cells. Stepping back onto the previous point undoes it.
+    pub fn extend_to(&mut self, target: Point) {
+        while let Some(&last) = self.points.last() {
+            if last == target {
+                break;
+            }
+            let next = Point::new(step(last.x, target.x), step(last.y, target.y));
+            let len = self.points.len();
+            if len > 1 && self.points[len - 2] == next {
+                self.points.pop();
+            } else {
+                self.points.push(next);
+            }
+        }
+    }
+
+    // True when the line touches the cell at (x, y), including its edges
+    pub fn touches(&self, x: u8, y: u8) -> bool {
+        let (min_x, min_y) = (2 * x as u16 - 2, 2 * y as u16 - 2);
+        self.points.iter().any(|p| {
+            (min_x..=min_x + 2).contains(&(p.x as u16))
+                && (min_y..=min_y + 2).contains(&(p.y as u16))
+        })
+    }
+}
+
+fn step(from: u8, to: u8) -> u8 {
+    if to > from {
+        from + 2.min(to - from)
+    } else {
+        from - 2.min(from - to)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Shape {
+    Square,
+    FilledSquare,
+    Triangle,
+    InvertedTriangle,
+    FilledDiamond,
+    Diamond,
+    FilledCircle,
+    Circle,
+    FilledStar,
+    Star,
+}
+
+impl Shape {
+    // In the order they're picked with the digit keys, 0 being the tenth
+    pub fn all() -> [Shape; 10] {
+        [
+            Shape::Square,
+            Shape::FilledSquare,
+            Shape::Triangle,
+            Shape::InvertedTriangle,
+            Shape::FilledDiamond,
+            Shape::Diamond,
+            Shape::FilledCircle,
+            Shape::Circle,
+            Shape::FilledStar,
+            Shape::Star,
+        ]
+    }
+
+    pub fn glyph(&self) -> &'static str {
+        match self {
+            Shape::Square => "□",
+            Shape::FilledSquare => "■",
+            Shape::Triangle => "▲",
+            Shape::InvertedTriangle => "▼",
+            Shape::FilledDiamond => "◆",
+            Shape::Diamond => "◇",
+            Shape::FilledCircle => "●",
+            Shape::Circle => "○",
+            Shape::FilledStar => "★",
+            Shape::Star => "☆",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Stamp {
+    pub shape: Shape,
+    pub color: u8,
+}
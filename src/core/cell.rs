@@ -1,3 +1,5 @@
+use crate::core::annotation::Stamp;
+use serde::{Deserialize, Serialize};
 use std::{
     collections::HashSet,
     fmt::{Debug, Formatter},
@@ -9,6 +11,11 @@ pub struct Cell {
     pub y: u8,
     pub value: u8,
     pub mutable: bool,
+    pub corner_marks: Vec<u8>,
+    pub center_marks: Vec<u8>,
+    pub colors: Vec<u8>,
+    pub stamps: Vec<Stamp>,
+    pub note: String,
     pub neighbors: HashSet<Point>,
 }
 
@@ -22,7 +29,7 @@ impl Debug for Cell {
     }
 }
 
-#[derive(Clone, Eq, PartialEq, Hash, Copy)]
+#[derive(Clone, Eq, PartialEq, Hash, Copy, Serialize, Deserialize)]
 pub struct Point {
     pub x: u8,
     pub y: u8,
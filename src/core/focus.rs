@@ -0,0 +1,96 @@
+use crate::core::{
+    cell::Point,
+    graph::{DigitFocus, Graph},
+};
+
+// Highlights that can be pinned, so they stay while the player works
+// somewhere else on the board
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FocusMode {
+    // Where the digit is or could still go
+    Digit(u8),
+    Row(u8),
+    Column(u8),
+    // Numbered across the whole board, left to right then down
+    Box(u8),
+    // Cells that see every one of these
+    Peers(Vec<u16>),
+}
+
+// Which mode a pin button makes, taken from the cursor or selection when
+// it's clicked
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FocusKind {
+    Digit,
+    Row,
+    Column,
+    Box,
+    Peers,
+}
+
+impl FocusKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            FocusKind::Digit => "Digit",
+            FocusKind::Row => "Row",
+            FocusKind::Column => "Column",
+            FocusKind::Box => "Box",
+            FocusKind::Peers => "Peers",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PinnedFocus {
+    pub mode: FocusMode,
+    pub active: bool,
+    // Which of the theme's focus layers it's drawn with
+    pub layer: usize,
+}
+
+// Boxes line up across every layout, since all the grids start on a box edge
+pub fn box_number(graph: &Graph, x: u8, y: u8) -> u8 {
+    let across = graph.width().div_ceil(3);
+    (y - 1) / 3 * across + (x - 1) / 3 + 1
+}
+
+impl FocusMode {
+    pub fn label(&self, graph: &Graph) -> String {
+        match self {
+            FocusMode::Digit(digit) => format!("Digit {}", digit),
+            FocusMode::Row(y) => format!("Row {}", y),
+            FocusMode::Column(x) => format!("Column {}", x),
+            FocusMode::Box(n) => format!("Box {}", n),
+            FocusMode::Peers(cells) => match cells.as_slice() {
+                [index] => match graph.cell(*index) {
+                    Some(cell) => format!("Peers of r{}c{}", cell.y, cell.x),
+                    None => "Peers of a cell".to_string(),
+                },
+                cells => format!("Peers of {} cells", cells.len()),
+            },
+        }
+    }
+
+    pub fn covers(&self, graph: &Graph, index: u16) -> bool {
+        let cell = match graph.cell(index) {
+            Some(cell) => cell,
+            None => return false,
+        };
+        match self {
+            FocusMode::Digit(digit) => matches!(
+                graph.digit_focus(index, *digit),
+                Some(DigitFocus::Placed | DigitFocus::Marked | DigitFocus::Candidate)
+            ),
+            FocusMode::Row(y) => cell.y == *y,
+            FocusMode::Column(x) => cell.x == *x,
+            FocusMode::Box(n) => box_number(graph, cell.x, cell.y) == *n,
+            FocusMode::Peers(cells) => {
+                let point = Point::new(cell.x, cell.y);
+                !cells.contains(&index)
+                    && cells
+                        .iter()
+                        .all(|&i| graph.cell(i).is_some_and(|c| c.neighbors.contains(&point)))
+            }
+        }
+    }
+}
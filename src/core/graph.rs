@@ -1,25 +1,90 @@
 use rand::prelude::*;
-use std::collections::{HashMap, HashSet};
+use std::collections::HashSet;
 use std::fmt::{Debug, Formatter};
 
+use crate::core::annotation::Annotations;
 use crate::core::cell::{Cell, Point};
+use crate::core::layout::{Layout, GRID_SIZE};
+use crate::core::puzzle::{Progress, Puzzle, PuzzleSettings};
 use crate::core::value::{from_val, options, to_val, Value};
 
-#[derive(Clone, Default)]
+// Upper bound on search steps before generation gives up on a layout
+const MAX_DEPTH: usize = 1_000_000;
+// Fresh grids tried before making a puzzle fails, each carrying on from the
+// same random numbers so a seed still always gives the same puzzle
+const FILL_ATTEMPTS: usize = 5;
+
+// Where a cell stands for the digit being focused on
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DigitFocus {
+    Placed,
+    // Holds another digit, or a neighbor already has this one
+    Excluded,
+    // Could still go here and the player has marked it
+    Marked,
+    Candidate,
+}
+
+// Player entries that don't agree with the solution
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Mistake {
+    // The wrong digit in a cell
+    Digit(u16, u8),
+    // Center marks that leave out the digit that goes there
+    Elimination(u16, u8),
+}
+
+impl Mistake {
+    pub fn index(&self) -> u16 {
+        match self {
+            Mistake::Digit(index, _) | Mistake::Elimination(index, _) => *index,
+        }
+    }
+
+    // Found mistakes stay shown until the player changes what was wrong
+    pub fn still_in(&self, graph: &Graph) -> bool {
+        match (self, graph.cell(self.index())) {
+            (Mistake::Digit(_, digit), Some(cell)) => cell.value == *digit,
+            (Mistake::Elimination(_, digit), Some(cell)) => {
+                cell.value == 0
+                    && !cell.center_marks.is_empty()
+                    && !cell.center_marks.contains(digit)
+            }
+            (_, None) => false,
+        }
+    }
+}
+
+#[derive(Clone, Default, PartialEq)]
 pub struct Graph {
+    pub annotations: Annotations,
     pub cells: Vec<Cell>,
+    pub layout: Layout,
 }
 
 impl Graph {
-    pub fn new() -> Graph {
-        let mut g = Graph { cells: Vec::new() };
-        (1..=9).for_each(|x| {
-            (1..=9).for_each(|y| {
+    pub fn with_layout(layout: Layout) -> Graph {
+        let mut g = Graph {
+            annotations: Annotations::default(),
+            cells: Vec::new(),
+            layout,
+        };
+        // Cells are kept sorted by (x, y) so `at` can binary search them
+        (1..=layout.width()).for_each(|x| {
+            (1..=layout.height()).for_each(|y| {
+                if !layout.contains(x, y) {
+                    return;
+                }
                 let c = Cell {
                     x,
                     y,
                     value: 0,
                     mutable: true,
+                    corner_marks: Vec::new(),
+                    center_marks: Vec::new(),
+                    colors: Vec::new(),
+                    stamps: Vec::new(),
+                    note: String::new(),
                     neighbors: HashSet::new(),
                 };
                 g.cells.push(c);
@@ -33,17 +98,38 @@ impl Graph {
         return g;
     }
 
+    pub fn width(&self) -> u8 {
+        self.layout.width()
+    }
+
+    pub fn height(&self) -> u8 {
+        self.layout.height()
+    }
+
     pub fn at(&self, x: u8, y: u8) -> Option<&Cell> {
-        let cell = self.cells.iter().find(|c| c.x == x && c.y == y);
-        return cell;
+        match self.cells.binary_search_by(|c| (c.x, c.y).cmp(&(x, y))) {
+            Ok(i) => self.cells.get(i),
+            Err(_) => None,
+        }
     }
 
-    pub fn index(&mut self, index: u8) -> Option<&mut Cell> {
-        let x = (index - 1) % 9 + 1;
-        let y = (index - 1) / 9 + 1;
+    // Indexes start at 1, so 0 is never a cell
+    pub fn cell(&self, index: u16) -> Option<&Cell> {
+        let width = self.width() as u16;
+        let i = index.checked_sub(1)?;
+        self.at((i % width + 1) as u8, (i / width + 1) as u8)
+    }
+
+    pub fn index(&mut self, index: u16) -> Option<&mut Cell> {
+        let width = self.width() as u16;
+        let i = index.checked_sub(1)?;
+        let x = (i % width + 1) as u8;
+        let y = (i / width + 1) as u8;
 
-        let cell = self.cells.iter_mut().find(|c| c.x == x && c.y == y);
-        return cell;
+        match self.cells.binary_search_by(|c| (c.x, c.y).cmp(&(x, y))) {
+            Ok(i) => self.cells.get_mut(i),
+            Err(_) => None,
+        }
     }
 
     fn neighbors_for(&self, cell: &Cell) -> HashSet<Point> {
@@ -51,62 +137,24 @@ impl Graph {
         let x = cell.x;
         let y = cell.y;
 
-        (1..=9).for_each(|i| {
-            if i != y {
-                neighbors.insert(Point { x, y: i });
-            }
-            if i != x {
-                neighbors.insert(Point { x: i, y });
-            }
-        });
-
         // y is the row (vertical)
         // x is the column (horizontal)
-        match (x, y) {
-            (1..=3, 1..=3) => {
-                neighbors.extend(Graph::box_set(1, 3, 1, 3).into_iter().map(|p| p.clone()))
-            }
-
-            (3..=6, 1..=3) => {
-                neighbors.extend(Graph::box_set(4, 6, 1, 3).into_iter().map(|p| p.clone()))
-            }
-
-            (6..=9, 1..=3) => {
-                neighbors.extend(Graph::box_set(7, 9, 1, 3).into_iter().map(|p| p.clone()))
-            }
-
-            #[allow(overlapping_range_endpoints)]
-            (1..=3, 3..=6) => {
-                neighbors.extend(Graph::box_set(1, 3, 4, 6).into_iter().map(|p| p.clone()))
-            }
-
-            #[allow(overlapping_range_endpoints)]
-            (3..=6, 3..=6) => {
-                neighbors.extend(Graph::box_set(4, 6, 4, 6).into_iter().map(|p| p.clone()))
-            }
-
-            #[allow(overlapping_range_endpoints)]
-            (6..=9, 3..=6) => {
-                neighbors.extend(Graph::box_set(7, 9, 4, 6).into_iter().map(|p| p.clone()))
-            }
-
-            #[allow(overlapping_range_endpoints)]
-            (1..=3, 6..=9) => {
-                neighbors.extend(Graph::box_set(1, 3, 7, 9).into_iter().map(|p| p.clone()))
-            }
-
-            #[allow(overlapping_range_endpoints)]
-            (3..=6, 6..=9) => {
-                neighbors.extend(Graph::box_set(4, 6, 7, 9).into_iter().map(|p| p.clone()))
-            }
+        // A cell shared by overlapping grids sees the row, column and box of each of them
+        for origin in self.layout.grids_at(x, y) {
+            (origin.y..origin.y + GRID_SIZE).for_each(|i| {
+                if i != y {
+                    neighbors.insert(Point { x, y: i });
+                }
+            });
+            (origin.x..origin.x + GRID_SIZE).for_each(|i| {
+                if i != x {
+                    neighbors.insert(Point { x: i, y });
+                }
+            });
 
-            #[allow(overlapping_range_endpoints)]
-            (6..=9, 6..=9) => {
-                neighbors.extend(Graph::box_set(7, 9, 7, 9).into_iter().map(|p| p.clone()))
-            }
-            _ => {
-                eprintln!("Incorrect X,Y range supplied!");
-            }
+            let min_x = origin.x + (x - origin.x) / 3 * 3;
+            let min_y = origin.y + (y - origin.y) / 3 * 3;
+            neighbors.extend(Graph::box_set(min_x, min_x + 2, min_y, min_y + 2));
         }
         return neighbors;
     }
@@ -121,113 +169,109 @@ impl Graph {
         return points;
     }
 
-    pub fn generate(&mut self) {
-        let max_depth = 1_000_000;
-        let mut depth = 0;
-        let mut rng = rand::thread_rng();
-        let mut graph: Graph;
-        let mut i = 0;
-        let mut invalid_moves: HashMap<usize, Vec<Value>> = HashMap::new();
-
-        while i < 81 {
-            match self.cells.get_mut(i) {
-                Some(cell) => {
-                    if !cell.mutable {
-                        i += 1;
-                        continue;
-                    }
-                }
-                None => {}
-            }
-            graph = self.clone();
-            let vals: HashSet<Value> = HashSet::new();
-
-            // At the start of each loop, we need to reset the values of all cells after the current one
-            let mut xi = i;
-            while xi < 81 {
-                let mut cell = self.cells.get_mut(xi).unwrap();
-                if cell.mutable {
-                    cell.value = 0;
-                }
-                xi += 1;
-                match invalid_moves.get(&xi) {
-                    Some(_) => {
-                        invalid_moves.insert(xi, vec![]);
-                    }
-                    None => {}
-                }
+    // Depth first search that always branches on the empty cell with the fewest
+    // possible values, so cells shared between grids are tried early.
+    fn fill<R: Rng>(&mut self, rng: &mut R, depth: &mut usize) -> bool {
+        *depth += 1;
+        if *depth >= MAX_DEPTH {
+            return false;
+        }
+
+        let (i, mut v) = match self.most_constrained() {
+            Some(best) => best,
+            None => return true,
+        };
+
+        v.sort();
+        v.shuffle(rng);
+        for n in v {
+            self.cells[i].value = from_val(n);
+            if self.fill(rng, depth) {
+                return true;
             }
+        }
+        self.cells[i].value = 0;
+        return false;
+    }
 
-            // Grab all possible valid moves for the current cell, and shuffle them, then pick the first one
-            let mut cell = self.cells.get_mut(i).unwrap();
-            let set = graph.possible_values(cell, vals);
-            let mut v: Vec<Value> = Vec::new();
-            for n in set {
-                match invalid_moves.get(&(i)) {
-                    Some(moves) => {
-                        if !moves.contains(&n) {
-                            v.push(n);
-                        }
-                    }
-                    None => {
-                        v.push(n);
-                        invalid_moves.insert(i, vec![]);
-                    }
+    // Empty cell with the fewest values left for it, stopping early at one with none
+    fn most_constrained(&self) -> Option<(usize, Vec<Value>)> {
+        let mut best: Option<(usize, Vec<Value>)> = None;
+        for (i, cell) in self.cells.iter().enumerate() {
+            if cell.value != 0 {
+                continue;
+            }
+            let v: Vec<Value> = self
+                .possible_values(cell, HashSet::new())
+                .into_iter()
+                .collect();
+            let fewer = match &best {
+                Some((_, b)) => v.len() < b.len(),
+                None => true,
+            };
+            if fewer {
+                let stuck = v.is_empty();
+                best = Some((i, v));
+                if stuck {
+                    break;
                 }
             }
-            v.shuffle(&mut rng);
-            let choice = v.get(0).cloned();
+        }
+        best
+    }
 
-            match choice {
-                Some(num) => {
-                    if cell.mutable {
-                        cell.value = from_val(num);
-                    }
-                }
-                None => {
-                    let mut last_cell_index = i - 1;
-                    let mut last_cell = self.cells.get(last_cell_index).unwrap();
-                    while last_cell_index > 0 && !last_cell.mutable {
-                        last_cell_index -= 1;
-                        last_cell = self.cells.get(last_cell_index).unwrap();
-                    }
-                    // If we have no valid moves, we need to backtrack, and
-                    // add the current cell's value to the list of invalid moves for the previous cell
-                    match invalid_moves.get(&(&last_cell_index)) {
-                        Some(moves) => {
-                            let mut moves = moves.clone();
-                            if i > 1 {
-                                match to_val(last_cell.value) {
-                                    Value::Unknown => {}
-                                    n => {
-                                        moves.push(n);
-                                    }
-                                }
-                                invalid_moves.insert(last_cell_index, moves);
-                            }
-                        }
-                        None => match to_val(last_cell.value) {
-                            Value::Unknown => {}
-                            n => {
-                                invalid_moves.insert(last_cell_index, vec![n]);
-                            }
-                        },
-                    }
+    // How many ways the empty cells can be filled in, counting no further than
+    // `limit`. A search that runs too long is taken to have hit the limit.
+    pub fn count_solutions(&self, limit: usize) -> usize {
+        let mut solver = Solver::new(self);
+        let count = solver.solutions(limit);
+        if solver.depth >= MAX_DEPTH {
+            return limit;
+        }
+        count
+    }
 
-                    i -= i - last_cell_index;
-                    i = i.clamp(0, 81);
+    // Every cell's value in order, which is how boards are stored without
+    // their marks
+    pub fn values(&self) -> Vec<u8> {
+        self.cells.iter().map(|c| c.value).collect()
+    }
 
-                    depth += 1;
+    // A board from stored values, with the filled in ones as givens. Nothing
+    // unless there's a value for every cell.
+    pub fn from_values(layout: Layout, values: &[u8]) -> Option<Graph> {
+        let mut graph = Graph::with_layout(layout);
+        if values.len() != graph.cells.len() || values.iter().any(|&v| v > 9) {
+            return None;
+        }
+        for (cell, &value) in graph.cells.iter_mut().zip(values) {
+            cell.value = value;
+            cell.mutable = value == 0;
+        }
+        Some(graph)
+    }
 
-                    if depth >= max_depth {
-                        break;
-                    } else {
-                        continue;
-                    }
-                }
-            }
-            i += 1;
+    // The puzzle's answer, worked out from the givens alone so the player's
+    // entries don't get in the way. Nothing if there's more than one.
+    pub fn solution(&self) -> Option<Graph> {
+        self.solve(&Progress::default())
+    }
+
+    // Same as `solution`, giving up with nothing once `progress` is cancelled
+    pub fn solve(&self, progress: &Progress) -> Option<Graph> {
+        let mut solved = self.clone();
+        for cell in solved.cells.iter_mut().filter(|c| c.mutable) {
+            cell.value = 0;
+        }
+        let mut solver = Solver::new(&solved);
+        solver.progress = progress.clone();
+        if solver.solutions(2) != 1 || solver.depth >= MAX_DEPTH || progress.cancelled() {
+            return None;
+        }
+        for (cell, value) in solved.cells.iter_mut().zip(solver.found?) {
+            cell.value = value;
         }
+        Some(solved)
     }
 
     fn possible_values(&self, cell: &Cell, mut vals: HashSet<Value>) -> HashSet<Value> {
@@ -237,12 +281,109 @@ impl Graph {
         return options().difference(&vals).cloned().collect();
     }
 
-    pub fn point_to_index(&self, x: u8, y: u8) -> u8 {
-        return x + (9 * (y - 1));
+    // Values not yet taken by any of the cell's neighbors
+    pub fn candidates(&self, cell: &Cell) -> Vec<u8> {
+        let mut candidates: Vec<u8> = self
+            .possible_values(cell, HashSet::new())
+            .into_iter()
+            .map(from_val)
+            .collect();
+        candidates.sort();
+        return candidates;
+    }
+
+    pub fn fill_candidates(&mut self) {
+        let candidates: Vec<Vec<u8>> = self.cells.iter().map(|c| self.candidates(c)).collect();
+        for (cell, candidates) in self.cells.iter_mut().zip(candidates) {
+            if cell.mutable && cell.value == 0 {
+                cell.center_marks = candidates;
+            }
+        }
+    }
+
+    // Removes a placed value from the corner and center marks of the cell's neighbors
+    pub fn eliminate(&mut self, index: u16, value: u8) {
+        let neighbors = match self.index(index) {
+            Some(cell) => cell.neighbors.clone(),
+            None => return,
+        };
+        for cell in self.cells.iter_mut() {
+            if neighbors.contains(&Point::new(cell.x, cell.y)) {
+                cell.corner_marks.retain(|&n| n != value);
+                cell.center_marks.retain(|&n| n != value);
+            }
+        }
+    }
+
+    pub fn has_marks(&self) -> bool {
+        self.cells
+            .iter()
+            .any(|c| !c.corner_marks.is_empty() || !c.center_marks.is_empty())
+    }
+
+    pub fn clear_marks(&mut self) {
+        for cell in self.cells.iter_mut() {
+            cell.corner_marks.clear();
+            cell.center_marks.clear();
+        }
+    }
+
+    pub fn has_entries(&self) -> bool {
+        self.has_marks()
+            || !self.annotations.lines.is_empty()
+            || self.cells.iter().any(|c| {
+                (c.mutable && c.value != 0) || !c.colors.is_empty() || !c.stamps.is_empty()
+            })
+    }
+
+    // Takes the board back to just the givens. Notes stay, they're about the
+    // puzzle more than the attempt at it.
+    pub fn restart(&mut self) {
+        self.clear_marks();
+        self.annotations.lines.clear();
+        for cell in self.cells.iter_mut() {
+            if cell.mutable {
+                cell.value = 0;
+            }
+            cell.colors.clear();
+            cell.stamps.clear();
+        }
+    }
+
+    pub fn digit_focus(&self, index: u16, digit: u8) -> Option<DigitFocus> {
+        let cell = self.cell(index)?;
+        if digit == 0 {
+            return None;
+        }
+        let focus = if cell.value == digit {
+            DigitFocus::Placed
+        } else if cell.value != 0
+            || cell
+                .neighbors
+                .iter()
+                .filter(|&&p| p != Point::new(cell.x, cell.y))
+                .any(|p| self.at(p.x, p.y).is_some_and(|n| n.value == digit))
+        {
+            DigitFocus::Excluded
+        } else if cell.corner_marks.contains(&digit) || cell.center_marks.contains(&digit) {
+            DigitFocus::Marked
+        } else {
+            DigitFocus::Candidate
+        };
+        Some(focus)
     }
 
-    pub fn invalid_cells(&self) -> Vec<u8> {
-        let mut invalid: Vec<u8> = Vec::new();
+    pub fn point_to_index(&self, x: u8, y: u8) -> u16 {
+        return x as u16 + (self.width() as u16 * (y as u16 - 1));
+    }
+
+    // Every cell filled in with nothing clashing
+    pub fn is_complete(&self) -> bool {
+        self.cells.iter().all(|c| c.value != 0) && self.invalid_cells().is_empty()
+    }
+
+    pub fn invalid_cells(&self) -> Vec<u16> {
+        let mut invalid: Vec<u16> = Vec::new();
         for cell in &self.cells {
             if cell.value == 0 {
                 continue;
@@ -260,25 +401,216 @@ impl Graph {
         return invalid;
     }
 
-    pub fn make_puzzle(remaining_clues: u8) -> Graph {
-        let mut graph = Graph::new();
-        graph.generate();
-        let mut rng = rand::thread_rng();
-        let mut i = 81;
-        while i > remaining_clues {
-            let idx = rng.gen_range(0..81);
-            let cell = graph.cells.get_mut(idx).unwrap();
-            if cell.value != 0 {
-                cell.value = 0;
-                i -= 1;
+    // Compares what the player's filled in with `solution`, and their center
+    // marks too if `marks` is set
+    pub fn mistakes(&self, solution: &Graph, marks: bool) -> Vec<Mistake> {
+        let mut mistakes = Vec::new();
+        for (cell, solved) in self.cells.iter().zip(&solution.cells) {
+            let index = self.point_to_index(cell.x, cell.y);
+            if cell.value != 0 && cell.value != solved.value {
+                mistakes.push(Mistake::Digit(index, cell.value));
+            }
+            let eliminated = cell.value == 0
+                && !cell.center_marks.is_empty()
+                && !cell.center_marks.contains(&solved.value);
+            if marks && eliminated {
+                mistakes.push(Mistake::Elimination(index, solved.value));
+            }
+        }
+        mistakes
+    }
+
+    // Fills a grid then takes clues away (in pairs when symmetric) for as long
+    // as the puzzle keeps a single solution, down to the difficulty's count.
+    // The filled grid comes back with it as the solution. Gives up with
+    // nothing if `progress` is cancelled part way, or no grid could be filled.
+    pub fn make_puzzle(settings: &PuzzleSettings, progress: &Progress) -> Option<Puzzle> {
+        let layout = settings.layout;
+        let mut rng = StdRng::seed_from_u64(settings.seed.unwrap_or_else(random));
+        let mut graph = Graph::with_layout(layout);
+        let mut filled = false;
+        for _ in 0..FILL_ATTEMPTS {
+            if progress.cancelled() {
+                return None;
             }
+            graph = Graph::with_layout(layout);
+            let mut depth = 0;
+            if graph.fill(&mut rng, &mut depth) {
+                filled = true;
+                break;
+            }
+        }
+        if !filled {
+            eprintln!("Unable to fill {} grid!", layout.name());
+            return None;
         }
+
+        let solution = graph.clone();
+
+        let target = layout.clues(settings.difficulty.clues_per_grid());
+        let mut clues = graph.cells.len();
+        let mut order: Vec<usize> = (0..graph.cells.len()).collect();
+        order.shuffle(&mut rng);
+        for (done, &i) in order.iter().enumerate() {
+            if progress.cancelled() {
+                return None;
+            }
+            // Done at the target or once every cell has been tried, whichever
+            // comes first
+            let removable = order.len().saturating_sub(target);
+            let removed = order.len() - clues;
+            progress.set(removed.max(done * removable / order.len()), removable);
+            if clues <= target {
+                break;
+            }
+            let cell = &graph.cells[i];
+            if cell.value == 0 {
+                continue;
+            }
+            let mut group = vec![i];
+            if let Some(p) = settings
+                .symmetry
+                .partner(layout, Point::new(cell.x, cell.y))
+            {
+                if let Some(j) = graph.cells.iter().position(|c| c.x == p.x && c.y == p.y) {
+                    group.push(j);
+                }
+            }
+
+            let values: Vec<u8> = group.iter().map(|&j| graph.cells[j].value).collect();
+            group.iter().for_each(|&j| graph.cells[j].value = 0);
+            if graph.count_solutions(2) == 1 {
+                clues -= group.len();
+            } else {
+                for (&j, value) in group.iter().zip(values) {
+                    graph.cells[j].value = value;
+                }
+            }
+        }
+
         graph
             .cells
             .iter_mut()
             .filter(|c| c.value != 0)
             .for_each(|c| c.mutable = false);
-        return graph;
+        Some(Puzzle { graph, solution })
+    }
+}
+
+// Values and neighbors by position in `cells`, with the values a cell can
+// still take as a bit set. Counting solutions runs the search many times over
+// for each puzzle, which is too slow going through points and hash sets.
+struct Solver {
+    values: Vec<u8>,
+    neighbors: Vec<Vec<usize>>,
+    depth: usize,
+    // The first solution the search came across
+    found: Option<Vec<u8>>,
+    // Checked as the search goes, a cancelled one stops as if it hit the limit
+    progress: Progress,
+}
+
+impl Solver {
+    fn new(graph: &Graph) -> Solver {
+        let neighbors = graph
+            .cells
+            .iter()
+            .map(|cell| {
+                cell.neighbors
+                    .iter()
+                    .filter_map(|p| {
+                        graph
+                            .cells
+                            .binary_search_by(|c| (c.x, c.y).cmp(&(p.x, p.y)))
+                            .ok()
+                    })
+                    .collect()
+            })
+            .collect();
+        Solver {
+            values: graph.cells.iter().map(|c| c.value).collect(),
+            neighbors,
+            depth: 0,
+            found: None,
+            progress: Progress::default(),
+        }
+    }
+
+    // Bit n is set when n can still go in the cell
+    fn allowed(&self, i: usize) -> u16 {
+        let taken = self.neighbors[i]
+            .iter()
+            .fold(0u16, |taken, &n| taken | 1 << self.values[n]);
+        0b11_1111_1110 & !taken
+    }
+
+    fn solutions(&mut self, limit: usize) -> usize {
+        self.depth += 1;
+        if self.depth >= MAX_DEPTH || self.progress.cancelled() {
+            return limit;
+        }
+
+        let mut best: Option<(usize, u16)> = None;
+        for i in 0..self.values.len() {
+            if self.values[i] != 0 {
+                continue;
+            }
+            let allowed = self.allowed(i);
+            if best.is_none_or(|(_, b)| allowed.count_ones() < b.count_ones()) {
+                best = Some((i, allowed));
+                if allowed == 0 {
+                    break;
+                }
+            }
+        }
+        let (i, allowed) = match best {
+            Some(best) => best,
+            None => {
+                if self.found.is_none() {
+                    self.found = Some(self.values.clone());
+                }
+                return 1;
+            }
+        };
+
+        let mut count = 0;
+        for n in 1..=9 {
+            if allowed & 1 << n == 0 {
+                continue;
+            }
+            self.values[i] = n;
+            count += self.solutions(limit - count);
+            if count >= limit {
+                break;
+            }
+        }
+        self.values[i] = 0;
+        count
+    }
+}
+
+impl Graph {
+    // Overlapping grids don't fit the box drawing below, so print them plainly
+    // with a gap between boxes and blanks where no grid covers the board.
+    fn multi_grid_string(&self) -> String {
+        let mut s = format!("{}\n", self.layout.name());
+        for y in 1..=self.height() {
+            if y > 1 && (y - 1) % 3 == 0 {
+                s.push('\n');
+            }
+            for x in 1..=self.width() {
+                if x > 1 && (x - 1) % 3 == 0 {
+                    s.push(' ');
+                }
+                match self.at(x, y) {
+                    Some(c) if c.value != 0 => s.push_str(&format!("{} ", c.value)),
+                    Some(_) => s.push_str(". "),
+                    None => s.push_str("  "),
+                }
+            }
+            s.push('\n');
+        }
+        s
     }
 }
 
@@ -304,6 +636,10 @@ impl Debug for Graph {
     //    "\033[#{direction == :up ? 1 : 2}J" // clear screen
 
     fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        if self.layout != Layout::Classic {
+            return write!(f, "{}", self.multi_grid_string());
+        }
+
         let mut s = String::new();
         s.push_str(&format!("{}", double_row_top()));
         for i in 0..9 {
@@ -332,3 +668,69 @@ impl Debug for Graph {
         write!(f, "{}", s)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::puzzle::{Difficulty, Symmetry};
+
+    fn settings(layout: Layout, seed: u64) -> PuzzleSettings {
+        PuzzleSettings {
+            layout,
+            difficulty: Difficulty::Medium,
+            symmetry: Symmetry::Rotational,
+            seed: Some(seed),
+        }
+    }
+
+    #[test]
+    fn make_puzzle_is_deterministic() {
+        for seed in [1, 42] {
+            let settings = settings(Layout::Classic, seed);
+            let made = Graph::make_puzzle(&settings, &Progress::default()).unwrap();
+            assert!(made == Graph::make_puzzle(&settings, &Progress::default()).unwrap());
+            let a = &made.graph;
+            assert_eq!(a.count_solutions(2), 1);
+            assert_eq!(a.solution().unwrap().values(), made.solution.values());
+
+            // Taking away a symmetric pair can go one past the target
+            let clues = a.cells.iter().filter(|c| c.value != 0).count();
+            assert!(clues + 1 >= Layout::Classic.clues(Difficulty::Medium.clues_per_grid()));
+            assert!(a.cells.iter().all(|c| c.mutable == (c.value == 0)));
+        }
+
+        let a = Graph::make_puzzle(&settings(Layout::Classic, 1), &Progress::default());
+        let b = Graph::make_puzzle(&settings(Layout::Classic, 2), &Progress::default());
+        assert!(a != b);
+    }
+
+    #[test]
+    fn make_puzzle_overlapping_grids() {
+        let settings = settings(Layout::Twodoku, 7);
+        let made = Graph::make_puzzle(&settings, &Progress::default()).unwrap();
+        assert_eq!(made.graph.count_solutions(2), 1);
+        assert!(made.solution.cells.iter().all(|c| c.value != 0));
+    }
+
+    #[test]
+    fn make_puzzle_cancelled() {
+        let progress = Progress::default();
+        progress.cancel();
+        assert!(Graph::make_puzzle(&settings(Layout::Classic, 1), &progress).is_none());
+    }
+
+    #[test]
+    fn solve_ignores_entries() {
+        let made = Graph::make_puzzle(&settings(Layout::Classic, 3), &Progress::default()).unwrap();
+        let mut played = made.graph.clone();
+        let cell = played.cells.iter_mut().find(|c| c.mutable).unwrap();
+        cell.value = 1;
+
+        let solved = played.solve(&Progress::default()).unwrap();
+        assert_eq!(solved.values(), made.solution.values());
+
+        let progress = Progress::default();
+        progress.cancel();
+        assert!(played.solve(&progress).is_none());
+    }
+}
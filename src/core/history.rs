@@ -0,0 +1,408 @@
+use crate::core::{
+    annotation::{Annotations, Stamp},
+    cell::Cell,
+    graph::Graph,
+};
+
+// The parts of a cell the player can change
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CellEdit {
+    pub value: u8,
+    pub corner_marks: Vec<u8>,
+    pub center_marks: Vec<u8>,
+    pub colors: Vec<u8>,
+    pub stamps: Vec<Stamp>,
+    pub note: String,
+}
+
+impl CellEdit {
+    fn of(cell: &Cell) -> CellEdit {
+        CellEdit {
+            value: cell.value,
+            corner_marks: cell.corner_marks.clone(),
+            center_marks: cell.center_marks.clone(),
+            colors: cell.colors.clone(),
+            stamps: cell.stamps.clone(),
+            note: cell.note.clone(),
+        }
+    }
+
+    fn restore(&self, cell: &mut Cell) {
+        cell.value = self.value;
+        cell.corner_marks = self.corner_marks.clone();
+        cell.center_marks = self.center_marks.clone();
+        cell.colors = self.colors.clone();
+        cell.stamps = self.stamps.clone();
+        cell.note = self.note.clone();
+    }
+}
+
+// Everything one player action changed, along with what was there before so
+// it can be reversed. Only the cells that actually changed are kept.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Command {
+    pub label: String,
+    pub cells: Vec<(u16, CellEdit, CellEdit)>,
+    pub annotations: Option<(Annotations, Annotations)>,
+}
+
+impl Command {
+    // Both graphs have to share a layout, cells are compared in order
+    pub fn diff(label: &str, before: &Graph, after: &Graph) -> Command {
+        let cells = before
+            .cells
+            .iter()
+            .zip(after.cells.iter())
+            .filter_map(|(b, a)| {
+                let (old, new) = (CellEdit::of(b), CellEdit::of(a));
+                if old == new {
+                    return None;
+                }
+                Some((after.point_to_index(a.x, a.y), old, new))
+            })
+            .collect();
+        let annotations = if before.annotations != after.annotations {
+            Some((before.annotations.clone(), after.annotations.clone()))
+        } else {
+            None
+        };
+        Command {
+            label: label.to_string(),
+            cells,
+            annotations,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty() && self.annotations.is_none()
+    }
+
+    fn undo(&self, graph: &mut Graph) {
+        for (index, before, _) in &self.cells {
+            if let Some(cell) = graph.index(*index) {
+                before.restore(cell);
+            }
+        }
+        if let Some((before, _)) = &self.annotations {
+            graph.annotations = before.clone();
+        }
+    }
+
+    fn redo(&self, graph: &mut Graph) {
+        for (index, _, after) in &self.cells {
+            if let Some(cell) = graph.index(*index) {
+                after.restore(cell);
+            }
+        }
+        if let Some((_, after)) = &self.annotations {
+            graph.annotations = after.clone();
+        }
+    }
+
+    // Folds a later command into this one, keeping the earliest before
+    fn merge(&mut self, later: Command) {
+        for (index, before, after) in later.cells {
+            match self.cells.iter_mut().find(|(i, _, _)| *i == index) {
+                Some(change) => change.2 = after,
+                None => self.cells.push((index, before, after)),
+            }
+        }
+        if let Some((before, after)) = later.annotations {
+            match &mut self.annotations {
+                Some(change) => change.1 = after,
+                None => self.annotations = Some((before, after)),
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+struct Node {
+    command: Command,
+    parent: usize,
+    children: Vec<usize>,
+    // Child redo goes to, the branch most recently undone out of or made
+    redo: Option<usize>,
+}
+
+// Undo history kept as a tree. Editing after an undo starts a new branch next
+// to the old one instead of throwing it away, so abandoned lines of reasoning
+// can be gone back to. Node 0 is the puzzle before any edits.
+#[derive(Clone, Debug, PartialEq)]
+pub struct History {
+    nodes: Vec<Node>,
+    current: usize,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        History {
+            nodes: vec![Node::default()],
+            current: 0,
+        }
+    }
+}
+
+impl History {
+    pub fn current(&self) -> usize {
+        self.current
+    }
+
+    pub fn label(&self, node: usize) -> &str {
+        &self.nodes[node].command.label
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    // Where `node` sits among the branches that split off with it, and how
+    // many of them there are
+    pub fn branches(&self, node: usize) -> (usize, usize) {
+        if node == 0 {
+            return (0, 1);
+        }
+        let siblings = &self.nodes[self.nodes[node].parent].children;
+        let i = siblings.iter().position(|&n| n == node).unwrap_or(0);
+        (i, siblings.len())
+    }
+
+    pub fn record(&mut self, command: Command) {
+        if command.is_empty() {
+            return;
+        }
+        let node = self.nodes.len();
+        self.nodes.push(Node {
+            command,
+            parent: self.current,
+            children: Vec::new(),
+            redo: None,
+        });
+        let parent = &mut self.nodes[self.current];
+        parent.children.push(node);
+        parent.redo = Some(node);
+        self.current = node;
+    }
+
+    // Adds to the last command instead of making a new one, as long as nothing
+    // has been undone or branched from it since
+    pub fn amend(&mut self, command: Command) {
+        if command.is_empty() {
+            return;
+        }
+        let node = &mut self.nodes[self.current];
+        if self.current == 0 || !node.children.is_empty() {
+            return self.record(command);
+        }
+        node.command.merge(command);
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.current != 0
+    }
+
+    pub fn can_redo(&self) -> bool {
+        self.nodes[self.current].redo.is_some()
+    }
+
+    pub fn undo(&mut self, graph: &mut Graph) -> bool {
+        if !self.can_undo() {
+            return false;
+        }
+        let node = &self.nodes[self.current];
+        node.command.undo(graph);
+        let (parent, undone) = (node.parent, self.current);
+        self.nodes[parent].redo = Some(undone);
+        self.current = parent;
+        true
+    }
+
+    pub fn redo(&mut self, graph: &mut Graph) -> bool {
+        match self.nodes[self.current].redo {
+            Some(next) => {
+                self.nodes[next].command.redo(graph);
+                self.current = next;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn ancestors(&self, mut node: usize) -> Vec<usize> {
+        let mut path = vec![node];
+        while node != 0 {
+            node = self.nodes[node].parent;
+            path.push(node);
+        }
+        path
+    }
+
+    // Undoes back to where `target`'s branch splits off, then redoes down it
+    pub fn goto(&mut self, target: usize, graph: &mut Graph) {
+        if target >= self.nodes.len() {
+            return;
+        }
+        let path = self.ancestors(target);
+        while !path.contains(&self.current) {
+            self.undo(graph);
+        }
+        let split = path.iter().position(|&n| n == self.current).unwrap();
+        for &node in path[..split].iter().rev() {
+            self.nodes[self.current].redo = Some(node);
+            self.redo(graph);
+        }
+    }
+
+    // The neighbouring branch at the closest point above the current edit
+    // where history split, `step` of -1 for the previous and 1 for the next
+    pub fn sibling(&self, step: isize) -> Option<usize> {
+        let mut node = self.current;
+        while node != 0 {
+            let siblings = &self.nodes[self.nodes[node].parent].children;
+            if siblings.len() > 1 {
+                let i = siblings.iter().position(|&n| n == node).unwrap() as isize;
+                let i = (i + step).rem_euclid(siblings.len() as isize);
+                return Some(siblings[i as usize]);
+            }
+            node = self.nodes[node].parent;
+        }
+        None
+    }
+
+    // The current branch from the first edit to the last one that can be
+    // redone, not including the root
+    pub fn branch(&self) -> Vec<usize> {
+        let mut path = self.ancestors(self.current);
+        path.pop();
+        path.reverse();
+        let mut node = self.current;
+        while let Some(next) = self.nodes[node].redo {
+            path.push(next);
+            node = next;
+        }
+        path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::layout::Layout;
+
+    fn value(graph: &Graph, index: u16) -> u8 {
+        graph.cell(index).unwrap().value
+    }
+
+    // Fills `index` with `digit` and records it as one step
+    fn fill(history: &mut History, graph: &mut Graph, index: u16, digit: u8) -> usize {
+        let before = graph.clone();
+        graph.index(index).unwrap().value = digit;
+        history.record(Command::diff(&format!("Fill {}", digit), &before, graph));
+        history.current()
+    }
+
+    #[test]
+    fn undo_and_redo() {
+        let mut graph = Graph::with_layout(Layout::Classic);
+        let mut history = History::default();
+        let start = graph.clone();
+        assert!(!history.can_undo() && !history.can_redo());
+
+        fill(&mut history, &mut graph, 1, 5);
+        fill(&mut history, &mut graph, 2, 6);
+        let filled = graph.clone();
+
+        assert!(history.undo(&mut graph));
+        assert_eq!((value(&graph, 1), value(&graph, 2)), (5, 0));
+        assert!(history.undo(&mut graph));
+        assert!(graph == start);
+        assert!(!history.undo(&mut graph));
+
+        assert!(history.redo(&mut graph));
+        assert!(history.redo(&mut graph));
+        assert!(graph == filled);
+        assert!(!history.redo(&mut graph));
+    }
+
+    #[test]
+    fn empty_commands_are_dropped() {
+        let graph = Graph::with_layout(Layout::Classic);
+        let mut history = History::default();
+        history.record(Command::diff("Nothing", &graph, &graph));
+        assert_eq!(history.len(), 1);
+        assert!(!history.can_undo());
+    }
+
+    #[test]
+    fn editing_after_undo_branches() {
+        let mut graph = Graph::with_layout(Layout::Classic);
+        let mut history = History::default();
+        let first = fill(&mut history, &mut graph, 1, 5);
+        let old = fill(&mut history, &mut graph, 2, 6);
+        history.undo(&mut graph);
+        let new = fill(&mut history, &mut graph, 2, 7);
+
+        // The old branch is kept next to the new one
+        assert_eq!(history.len(), 4);
+        assert_eq!(history.branches(old), (0, 2));
+        assert_eq!(history.branches(new), (1, 2));
+        assert_eq!(history.branch(), vec![first, new]);
+        assert_eq!(history.sibling(1), Some(old));
+        assert_eq!(history.sibling(-1), Some(old));
+
+        // Redo follows the branch most recently made
+        history.undo(&mut graph);
+        history.redo(&mut graph);
+        assert_eq!(value(&graph, 2), 7);
+    }
+
+    #[test]
+    fn goto_crosses_branches() {
+        let mut graph = Graph::with_layout(Layout::Classic);
+        let mut history = History::default();
+        fill(&mut history, &mut graph, 1, 5);
+        let old = fill(&mut history, &mut graph, 2, 6);
+        let old_end = fill(&mut history, &mut graph, 3, 4);
+        history.undo(&mut graph);
+        history.undo(&mut graph);
+        let new = fill(&mut history, &mut graph, 2, 7);
+
+        history.goto(old_end, &mut graph);
+        assert_eq!(history.current(), old_end);
+        assert_eq!(
+            (value(&graph, 1), value(&graph, 2), value(&graph, 3)),
+            (5, 6, 4)
+        );
+
+        history.goto(new, &mut graph);
+        assert_eq!(
+            (value(&graph, 1), value(&graph, 2), value(&graph, 3)),
+            (5, 7, 0)
+        );
+        // Redo from here stays on the new branch
+        assert!(!history.can_redo());
+
+        history.goto(0, &mut graph);
+        assert!(graph == Graph::with_layout(Layout::Classic));
+        assert_eq!(history.branch().len(), 2);
+        assert_eq!(history.branches(old), (0, 2));
+
+        // Out of range is ignored
+        history.goto(99, &mut graph);
+        assert_eq!(history.current(), 0);
+    }
+
+    #[test]
+    fn amend_merges_into_the_last_step() {
+        let mut graph = Graph::with_layout(Layout::Classic);
+        let mut history = History::default();
+        fill(&mut history, &mut graph, 1, 5);
+        let before = graph.clone();
+        graph.index(2).unwrap().value = 6;
+        history.amend(Command::diff("Fill 6", &before, &graph));
+
+        assert_eq!(history.len(), 2);
+        history.undo(&mut graph);
+        assert!(graph == Graph::with_layout(Layout::Classic));
+    }
+}
@@ -0,0 +1,142 @@
+use serde::{Deserialize, Serialize};
+
+use crate::core::cell::Point;
+
+// Each grid is placed by the (x, y) of its top-left cell. Grids that overlap
+// share the cells (and boxes) where their areas intersect.
+pub const GRID_SIZE: u8 = 9;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Layout {
+    #[default]
+    Classic,
+    Twodoku,
+    Butterfly,
+    Samurai,
+}
+
+impl Layout {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Layout::Classic => "Classic",
+            Layout::Twodoku => "Twodoku",
+            Layout::Butterfly => "Butterfly",
+            Layout::Samurai => "Samurai",
+        }
+    }
+
+    pub fn next(&self) -> Layout {
+        match self {
+            Layout::Classic => Layout::Twodoku,
+            Layout::Twodoku => Layout::Butterfly,
+            Layout::Butterfly => Layout::Samurai,
+            Layout::Samurai => Layout::Classic,
+        }
+    }
+
+    pub fn origins(&self) -> Vec<Point> {
+        match self {
+            Layout::Classic => vec![Point::new(1, 1)],
+            // Two grids sharing a single box on the diagonal
+            Layout::Twodoku => vec![Point::new(1, 1), Point::new(7, 7)],
+            // Four grids offset by one box, all sharing the center
+            Layout::Butterfly => vec![
+                Point::new(1, 1),
+                Point::new(4, 1),
+                Point::new(1, 4),
+                Point::new(4, 4),
+            ],
+            // Four corner grids each sharing a box with the center grid
+            Layout::Samurai => vec![
+                Point::new(1, 1),
+                Point::new(13, 1),
+                Point::new(7, 7),
+                Point::new(1, 13),
+                Point::new(13, 13),
+            ],
+        }
+    }
+
+    pub fn width(&self) -> u8 {
+        self.origins()
+            .iter()
+            .map(|o| o.x + GRID_SIZE - 1)
+            .max()
+            .unwrap_or(GRID_SIZE)
+    }
+
+    pub fn height(&self) -> u8 {
+        self.origins()
+            .iter()
+            .map(|o| o.y + GRID_SIZE - 1)
+            .max()
+            .unwrap_or(GRID_SIZE)
+    }
+
+    // Origins of every grid the point belongs to
+    pub fn grids_at(&self, x: u8, y: u8) -> Vec<Point> {
+        self.origins()
+            .into_iter()
+            .filter(|o| x >= o.x && x < o.x + GRID_SIZE && y >= o.y && y < o.y + GRID_SIZE)
+            .collect()
+    }
+
+    pub fn contains(&self, x: u8, y: u8) -> bool {
+        !self.grids_at(x, y).is_empty()
+    }
+
+    pub fn cell_count(&self) -> usize {
+        let mut count = 0;
+        (1..=self.width()).for_each(|x| {
+            (1..=self.height()).for_each(|y| {
+                if self.contains(x, y) {
+                    count += 1;
+                }
+            });
+        });
+        count
+    }
+
+    // Scales a clue count given for a single 9x9 grid to the whole layout
+    pub fn clues(&self, clues_per_grid: usize) -> usize {
+        clues_per_grid * self.cell_count() / 81
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cell_count() {
+        assert_eq!(Layout::Classic.cell_count(), 81);
+        // One shared box
+        assert_eq!(Layout::Twodoku.cell_count(), 2 * 81 - 9);
+        // A 12x12 square, every cell in at least one grid
+        assert_eq!(Layout::Butterfly.cell_count(), 144);
+        // Four boxes shared with the center grid
+        assert_eq!(Layout::Samurai.cell_count(), 5 * 81 - 4 * 9);
+    }
+
+    #[test]
+    fn grids_at() {
+        let at = |layout: Layout, x, y| layout.grids_at(x, y);
+
+        assert!(at(Layout::Classic, 1, 1) == vec![Point::new(1, 1)]);
+        assert!(at(Layout::Classic, 9, 9) == vec![Point::new(1, 1)]);
+        assert!(at(Layout::Classic, 10, 1).is_empty());
+
+        assert!(at(Layout::Twodoku, 8, 8) == vec![Point::new(1, 1), Point::new(7, 7)]);
+        assert!(at(Layout::Twodoku, 15, 15) == vec![Point::new(7, 7)]);
+        assert!(at(Layout::Twodoku, 12, 1).is_empty());
+
+        assert_eq!(at(Layout::Butterfly, 6, 6).len(), 4);
+        assert!(at(Layout::Butterfly, 1, 12) == vec![Point::new(1, 4)]);
+        assert!(at(Layout::Butterfly, 12, 4) == vec![Point::new(4, 1), Point::new(4, 4)]);
+
+        assert!(at(Layout::Samurai, 11, 11) == vec![Point::new(7, 7)]);
+        assert!(at(Layout::Samurai, 14, 14) == vec![Point::new(7, 7), Point::new(13, 13)]);
+        assert!(at(Layout::Samurai, 10, 1).is_empty());
+        assert!(at(Layout::Samurai, 21, 21) == vec![Point::new(13, 13)]);
+    }
+}
@@ -1,3 +1,8 @@
+pub mod annotation;
 pub mod cell;
+pub mod focus;
 pub mod graph;
+pub mod history;
+pub mod layout;
+pub mod puzzle;
 pub mod value;
@@ -0,0 +1,148 @@
+use std::sync::{
+    atomic::{AtomicBool, AtomicU32, Ordering},
+    Arc,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::{cell::Point, graph::Graph, layout::Layout};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Medium,
+    Hard,
+    Expert,
+}
+
+impl Difficulty {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Difficulty::Easy => "Easy",
+            Difficulty::Medium => "Medium",
+            Difficulty::Hard => "Hard",
+            Difficulty::Expert => "Expert",
+        }
+    }
+
+    pub fn next(&self) -> Difficulty {
+        match self {
+            Difficulty::Easy => Difficulty::Medium,
+            Difficulty::Medium => Difficulty::Hard,
+            Difficulty::Hard => Difficulty::Expert,
+            Difficulty::Expert => Difficulty::Easy,
+        }
+    }
+
+    // Clues left in each 9x9 grid. Generation stops early when no more can be
+    // taken away without a second solution turning up.
+    pub fn clues_per_grid(&self) -> usize {
+        match self {
+            Difficulty::Easy => 40,
+            Difficulty::Medium => 32,
+            Difficulty::Hard => 26,
+            Difficulty::Expert => 22,
+        }
+    }
+}
+
+// Which cells have their clues removed together
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Symmetry {
+    None,
+    // Half turn about the center of the board
+    #[default]
+    Rotational,
+    // Left to right
+    Mirror,
+}
+
+impl Symmetry {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Symmetry::None => "None",
+            Symmetry::Rotational => "Rotational",
+            Symmetry::Mirror => "Mirror",
+        }
+    }
+
+    pub fn next(&self) -> Symmetry {
+        match self {
+            Symmetry::None => Symmetry::Rotational,
+            Symmetry::Rotational => Symmetry::Mirror,
+            Symmetry::Mirror => Symmetry::None,
+        }
+    }
+
+    // Cell that mirrors `point`, which may be off the board for layouts that
+    // aren't symmetric the same way
+    pub fn partner(&self, layout: Layout, point: Point) -> Option<Point> {
+        let (w, h) = (layout.width(), layout.height());
+        let partner = match self {
+            Symmetry::None => return None,
+            Symmetry::Rotational => Point::new(w + 1 - point.x, h + 1 - point.y),
+            Symmetry::Mirror => Point::new(w + 1 - point.x, point.y),
+        };
+        match layout.contains(partner.x, partner.y) && partner != point {
+            true => Some(partner),
+            false => None,
+        }
+    }
+}
+
+// Everything a puzzle is made from. The same settings with the same seed
+// always give the same puzzle.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct PuzzleSettings {
+    pub layout: Layout,
+    pub difficulty: Difficulty,
+    pub symmetry: Symmetry,
+    // Picked at random when generating if left empty
+    pub seed: Option<u64>,
+}
+
+impl PuzzleSettings {
+    // Picks the seed now if there isn't one, so the puzzle can be made again
+    pub fn with_seed(self) -> PuzzleSettings {
+        PuzzleSettings {
+            seed: Some(self.seed.unwrap_or_else(rand::random)),
+            ..self
+        }
+    }
+}
+
+// A new puzzle along with the full grid its clues were taken from, so
+// checking it never has to solve it again
+#[derive(Clone, Debug, PartialEq)]
+pub struct Puzzle {
+    pub graph: Graph,
+    pub solution: Graph,
+}
+
+// Shared with a puzzle being made on another thread, which reports how far
+// along it is here and gives up once it's cancelled
+#[derive(Clone, Debug, Default)]
+pub struct Progress {
+    percent: Arc<AtomicU32>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl Progress {
+    pub fn set(&self, done: usize, total: usize) {
+        let percent = (done * 100 / total.max(1)) as u32;
+        self.percent.store(percent, Ordering::Relaxed);
+    }
+
+    pub fn percent(&self) -> u32 {
+        self.percent.load(Ordering::Relaxed)
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
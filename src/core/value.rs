@@ -4,9 +4,23 @@ use std::{
 };
 
 use bevy::reflect::{FromReflect, Reflect};
+use serde::{Deserialize, Serialize};
 use Value::*;
 
-#[derive(Reflect, Clone, Eq, PartialEq, Hash, Copy, Debug, PartialOrd, Ord, Default)]
+#[derive(
+    Reflect,
+    Clone,
+    Eq,
+    PartialEq,
+    Hash,
+    Copy,
+    Debug,
+    PartialOrd,
+    Ord,
+    Default,
+    Serialize,
+    Deserialize,
+)]
 pub enum Value {
     One,
     Two,
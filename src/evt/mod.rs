@@ -1,9 +1,92 @@
 use crate::{
-    core::value::Value,
-    rsc::game_state::{Action, GameState, Modifier, MouseState, Tools},
+    core::{
+        annotation::Line,
+        focus::FocusMode,
+        graph::Graph,
+        layout::Layout,
+        puzzle::{Puzzle, PuzzleSettings},
+        value::Value,
+    },
+    rsc::{
+        colors::Theme,
+        game_state::{Action, NoteTarget, PuzzleDialog, Tools},
+    },
 };
 
 pub struct ToolSelectedEvent(pub Tools);
-pub struct FocusModeEvent(pub Value);
 
-pub struct CornerMarkEvent(pub u8, pub Value);
+pub struct CornerMarkEvent(pub u16, pub Value);
+
+pub struct LayoutChangedEvent(pub Layout);
+
+pub struct GameStartedEvent(pub PuzzleSettings);
+
+// Asks for the next theme, from the theme button or its key
+pub struct NextThemeEvent;
+
+// The theme in game state was swapped for another, or its file was edited
+pub struct ThemeChangedEvent;
+
+// Asks to change how the board is shown, from the accessibility buttons or keys
+pub enum AccessibilityEvent {
+    ToggleCues,
+    // Steps of a tenth up or down
+    TextSize(i8),
+}
+
+// A game that's over, either finished or left for another one
+pub struct GameOverEvent {
+    pub puzzle: PuzzleSettings,
+    pub elapsed: f64,
+    pub hints: usize,
+    pub mistakes: usize,
+    pub completed: bool,
+}
+
+// Every change to the game goes through one of these and is applied by the
+// reducer system, so all views see the same state and it can be logged and
+// replayed. The mouse and modifier keys held aren't game state, they're kept
+// apart in `InputState`.
+#[derive(Clone, Debug)]
+pub enum StateEvent {
+    Action(Action),
+    Tool(Tools),
+    Select(Selection),
+    Cursor(u16),
+    Focus(Value),
+    // Pinned focus modes, by their place in the list
+    Pin(FocusMode),
+    TogglePin(usize),
+    Unpin(usize),
+    Layout(Layout),
+    Palette(usize),
+    Color(u8),
+    AutoEliminate(bool),
+    CheckMarks(bool),
+    Pause(bool),
+    // Seconds since the last frame, while the clock's running
+    Tick(f64),
+    EditNote(Option<NoteTarget>),
+    Note(NoteTarget, String),
+    // A pen line that hasn't been let go of yet
+    Drawing(Option<Line>),
+    // Screens over the board, and which key binding is waiting for a key
+    Settings(bool),
+    Stats(bool),
+    // A different theme picked, or the current one's file edited
+    Theme(Box<Theme>),
+    Rebind(Option<usize>),
+    PuzzleDialog(Option<PuzzleDialog>),
+    // Results of work done off the main thread. Boxed like the theme, so the
+    // events sent every frame and kept in the log stay small.
+    Generated(PuzzleSettings, Box<Puzzle>),
+    // The solution to fill the board in from
+    Solved(Box<Graph>),
+}
+
+#[derive(Clone, Debug)]
+pub enum Selection {
+    Set(Vec<u16>),
+    Add(Vec<u16>),
+    Toggle(u16),
+}
@@ -4,14 +4,32 @@ mod rsc;
 mod sys;
 mod ui;
 use crate::core::graph::Graph;
+use crate::core::puzzle::{Progress, PuzzleSettings};
+use crate::rsc::event_log::EventLog;
 use crate::rsc::game_state::GameState;
 use crate::rsc::game_state::Tools;
+use crate::rsc::generator::Generator;
+use crate::rsc::input_state::InputState;
+use crate::rsc::keymap::{Keymap, KEYMAP_PATH};
+use crate::rsc::pool::{PuzzlePool, POOL_PATH};
+use crate::rsc::prefs::{Prefs, PREFS_PATH};
+use crate::rsc::save::{SaveGame, SAVE_PATH};
+use crate::rsc::stats::{Stats, STATS_PATH};
+use crate::rsc::themes::{themes_dir, Themes};
+use crate::rsc::view::BoardView;
 
 use bevy::{
-    diagnostic::FrameTimeDiagnosticsPlugin, prelude::*, window::PresentMode, winit::WinitSettings,
+    diagnostic::FrameTimeDiagnosticsPlugin,
+    prelude::*,
+    utils::Duration,
+    window::PresentMode,
+    winit::{UpdateMode, WinitSettings},
 };
 use bevy_inspector_egui::quick::WorldInspectorPlugin;
-use ui::{ToolButton, ToolLabel};
+use ui::{ToolButton, ToolLabel, TOOL_PANEL_WIDTH};
+
+const WINDOW_WIDTH: f32 = 1920.;
+const WINDOW_HEIGHT: f32 = 1080.;
 
 fn main() {
     println!("Welcome to Sudoku!");
@@ -36,18 +54,71 @@ fn main() {
        TODO: -- Add note field to cells
     */
 
-    let g = Graph::make_puzzle(25);
+    // Pick up where the last saved game left off. A new puzzle made here is
+    // a game started for the stats, a saved one was counted when it began.
+    let mut started = None;
+    let mut game_state = match SaveGame::load(SAVE_PATH) {
+        Ok(save) => {
+            let mut game_state = GameState::new(save.to_graph());
+            game_state.restore(&save);
+            game_state
+        }
+        Err(_) => {
+            let settings = PuzzleSettings::default().with_seed();
+            started = Some(settings);
+            let made = Graph::make_puzzle(&settings, &Progress::default())
+                .expect("unable to make the first puzzle");
+            GameState {
+                puzzle: settings,
+                solution: Some(made.solution),
+                ..GameState::new(made.graph)
+            }
+        }
+    };
+
+    println!("{:?}", game_state.graph);
+
+    let prefs = Prefs::load(PREFS_PATH).unwrap_or_default();
+    let mut themes = Themes::load(&themes_dir());
+    themes.select(&prefs.theme);
+    game_state.theme = themes.current();
+
+    let stats = Stats::load(STATS_PATH);
 
-    println!("{:?}", g);
+    let mut pool = PuzzlePool::load(POOL_PATH)
+        .map_err(|e| eprintln!("Unable to load puzzle pool, starting an empty one: {}", e))
+        .unwrap_or_default();
+    pool.want(game_state.puzzle);
+
+    let keymap = Keymap::load(KEYMAP_PATH)
+        .map_err(|e| eprintln!("Unable to load key bindings, using the defaults: {}", e))
+        .unwrap_or_default();
+    for chord in keymap.conflicts() {
+        eprintln!(
+            "{} is bound more than once in {}",
+            chord.label(),
+            KEYMAP_PATH
+        );
+    }
 
     App::new()
         .insert_resource(ClearColor(Color::rgb(1.0, 0.0, 1.0))) //Set obnoxious clear color to ensure UI covers everything
-        .insert_resource(WinitSettings::desktop_app())
+        // Woken up often enough to keep the clock ticking, and to pick up
+        // theme files edited while the window's in the background
+        .insert_resource(WinitSettings {
+            focused_mode: UpdateMode::Reactive {
+                max_wait: Duration::from_millis(500),
+            },
+            unfocused_mode: UpdateMode::ReactiveLowPower {
+                max_wait: Duration::from_secs(1),
+            },
+            ..WinitSettings::desktop_app()
+        })
         .add_plugins(DefaultPlugins.set(WindowPlugin {
             window: WindowDescriptor {
                 title: "Sudoku!".to_string(),
-                width: 1920.,
-                height: 1080.,
+                width: WINDOW_WIDTH,
+                height: WINDOW_HEIGHT,
                 present_mode: PresentMode::AutoVsync,
                 decorations: true,
                 mode: bevy::window::WindowMode::Windowed,
@@ -58,25 +129,110 @@ fn main() {
         .add_plugin(FrameTimeDiagnosticsPlugin::default())
         .add_plugin(WorldInspectorPlugin)
         .add_event::<evt::ToolSelectedEvent>()
-        .add_event::<evt::FocusModeEvent>()
         .add_event::<evt::CornerMarkEvent>()
-        .insert_resource(GameState::new(g))
+        .add_event::<evt::LayoutChangedEvent>()
+        .add_event::<evt::GameStartedEvent>()
+        .add_event::<evt::GameOverEvent>()
+        .add_event::<evt::NextThemeEvent>()
+        .add_event::<evt::ThemeChangedEvent>()
+        .add_event::<evt::AccessibilityEvent>()
+        .add_event::<evt::StateEvent>()
+        .insert_resource(BoardView {
+            text_scale: prefs.text_scale,
+            ..BoardView::fit(
+                game_state.graph.layout,
+                Vec2::new(WINDOW_WIDTH - TOOL_PANEL_WIDTH, WINDOW_HEIGHT),
+            )
+        })
+        .insert_resource(EventLog::new(game_state.clone()))
+        .insert_resource(game_state)
+        .init_resource::<InputState>()
+        .insert_resource(keymap)
+        .init_resource::<Generator>()
+        .insert_resource(pool)
+        .insert_resource(stats)
+        .insert_resource(prefs)
+        .insert_resource(themes)
         .add_startup_system(setup)
+        .add_startup_system(move |mut events: EventWriter<evt::GameStartedEvent>| {
+            if let Some(settings) = started {
+                events.send(evt::GameStartedEvent(settings));
+            }
+        })
         .add_startup_system(ui::board.before(sys::grid_fill_system::grid_fill_system))
         .add_startup_system(ui::tool_panel)
+        .add_startup_system(ui::note_tooltip)
+        .add_startup_system(ui::progress_panel)
         .add_startup_system(sys::grid_fill_system::grid_fill_system)
         .add_system(sys::input::mouse_system)
         .add_system(sys::button_system::button_system)
         .add_system(sys::grid_update_system::grid_update_system)
-        .add_system(sys::grid_update_system::focus_mode_system)
         .add_system(sys::grid_update_system::corner_mark_system)
         .add_system(sys::grid_update_system::corner_mark_update_system)
+        .add_system(sys::grid_update_system::cell_color_system)
+        .add_system(sys::grid_update_system::stamp_update_system)
+        .add_system(sys::annotation_system::pen_system.before(sys::actions::reducer_system))
+        .add_system(sys::annotation_system::line_render_system)
         .add_system(sys::text::text_update_system)
         .add_system(sys::text::text_color_system)
+        .add_system(sys::text::mistake_counter_system)
         .add_system(sys::input::keyboard_system)
-        .add_system(sys::actions::action_system)
+        .add_system(sys::actions::reducer_system)
+        .add_system(
+            sys::note_system::note_input_system
+                .after(sys::input::keyboard_system)
+                .before(sys::actions::reducer_system),
+        )
+        .add_system(sys::note_system::notes_panel_system)
+        .add_system(sys::note_system::notes_text_system)
+        .add_system(sys::note_system::note_indicator_system)
+        .add_system(sys::note_system::note_tooltip_system)
+        .add_system(sys::history_system::history_panel_system)
+        .add_system(sys::history_system::history_row_system)
+        .add_system(sys::history_system::action_button_system)
+        .add_system(sys::focus_system::focus_panel_system)
+        .add_system(sys::focus_system::pin_button_system)
+        .add_system(sys::focus_system::pin_row_system)
+        .add_system(sys::puzzle_dialog_system::puzzle_dialog_screen_system)
+        .add_system(
+            sys::puzzle_dialog_system::puzzle_button_system.before(sys::actions::reducer_system),
+        )
+        .add_system(
+            sys::puzzle_dialog_system::puzzle_dialog_input_system
+                .after(sys::input::keyboard_system)
+                .before(sys::actions::reducer_system),
+        )
+        .add_system(sys::puzzle_dialog_system::puzzle_dialog_text_system)
+        .add_system(sys::clock_system::clock_system.before(sys::actions::reducer_system))
+        .add_system(sys::clock_system::clock_button_system)
+        .add_system(sys::clock_system::pause_screen_system)
+        .add_system(sys::clock_system::resume_button_system)
+        .add_system(sys::completion_system::completion_screen_system)
+        .add_system(sys::completion_system::completion_button_system)
+        .add_system(sys::generator_system::generator_system)
+        .add_system(sys::generator_system::progress_system)
+        .add_system(sys::settings_system::settings_screen_system)
+        .add_system(sys::settings_system::settings_button_system)
+        .add_system(
+            sys::settings_system::rebind_system
+                .after(sys::input::keyboard_system)
+                .before(sys::actions::reducer_system),
+        )
+        .add_system(sys::settings_system::settings_row_system)
+        .add_system(sys::stats_system::stats_system.after(sys::actions::reducer_system))
+        .add_system(sys::stats_system::stats_screen_system)
+        .add_system(sys::stats_system::stats_button_system)
+        .add_system(sys::theme_system::theme_system.before(sys::actions::reducer_system))
+        .add_system(sys::theme_system::theme_recolor_system)
+        .add_system(sys::theme_system::theme_button_system)
+        .add_system(sys::accessibility_system::accessibility_system)
+        .add_system(sys::accessibility_system::accessibility_button_system)
+        .add_system(sys::accessibility_system::cue_system)
         .add_system(tool_panel_system)
         .add_system(tool_panel_update_system)
+        .add_system(sys::board_view_system::board_zoom_system)
+        .add_system(sys::board_view_system::board_view_system)
+        .add_system(sys::board_view_system::board_rebuild_system)
         .run();
 }
 
@@ -86,13 +242,12 @@ fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
     // commands.spawn(ui::fps(&asset_server));
 }
 
+type ToolButtonFilter = (Changed<Interaction>, With<Button>, With<ToolButton>);
+
 fn tool_panel_system(
-    mut game_state: ResMut<GameState>,
-    mut tool_selected_event: EventWriter<evt::ToolSelectedEvent>,
-    mut interaction_query: Query<
-        (&Interaction, &mut BackgroundColor, &Children),
-        (Changed<Interaction>, With<Button>, With<ToolButton>),
-    >,
+    game_state: Res<GameState>,
+    mut state_events: EventWriter<evt::StateEvent>,
+    mut interaction_query: Query<(&Interaction, &mut BackgroundColor, &Children), ToolButtonFilter>,
     mut btn_query: Query<&ToolLabel>,
 ) {
     for (interaction, mut color, children) in &mut interaction_query {
@@ -101,8 +256,7 @@ fn tool_panel_system(
             Interaction::Clicked => {
                 match label {
                     ToolLabel { tool } => {
-                        game_state.tool = *tool;
-                        tool_selected_event.send(evt::ToolSelectedEvent(*tool));
+                        state_events.send(evt::StateEvent::Tool(*tool));
                     }
                 }
                 *color = BackgroundColor(game_state.theme.tool.selected);
@@ -123,15 +277,21 @@ fn tool_panel_system(
 }
 
 fn tool_panel_update_system(
-    game_state: ResMut<GameState>,
+    game_state: Res<GameState>,
     mut tool_selected_event: EventReader<evt::ToolSelectedEvent>,
+    mut theme_changed_event: EventReader<evt::ThemeChangedEvent>,
     mut tool_query: Query<(&ToolButton, &mut BackgroundColor)>,
 ) {
-    for event in tool_selected_event.iter() {
+    // A new theme colors them again for the tool that's selected
+    let mut selected: Vec<Tools> = tool_selected_event.iter().map(|e| e.0).collect();
+    if theme_changed_event.iter().count() > 0 {
+        selected.push(game_state.tool);
+    }
+    for selected in selected {
         for (tool, mut color) in &mut tool_query.iter_mut() {
             match tool {
                 ToolButton(tool) => {
-                    if *tool == event.0 {
+                    if *tool == selected {
                         *color = BackgroundColor(game_state.theme.tool.selected);
                     } else {
                         *color = BackgroundColor(game_state.theme.tool.bg);
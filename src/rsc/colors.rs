@@ -1,27 +1,66 @@
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
-#[derive(Reflect, Clone, Debug, Default)]
+#[derive(Reflect, Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
 pub struct Theme {
+    pub name: String,
     pub grid: GridTheme,
+    pub palettes: Vec<Palette>,
     pub tool: ToolTheme,
     pub window_bg: Color,
 }
 
-#[derive(Reflect, Clone, Debug, Default)]
+// Colors the player can paint cells with, picked by digit
+#[derive(Reflect, FromReflect, Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Palette {
+    pub name: String,
+    pub colors: Vec<Color>,
+}
+
+impl Palette {
+    // Palette colors are 1 based to match the digit keys that pick them
+    pub fn color(&self, n: u8) -> Color {
+        self.colors
+            .get((n as usize).wrapping_sub(1))
+            .cloned()
+            .unwrap_or(Color::NONE)
+    }
+}
+
+#[derive(Reflect, Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
 pub struct GridTheme {
     pub bg: Color,
     pub border: Color,
     pub cursor: Color,
     pub focused: Color,
+    // Layers for the rest of the board while a digit is focused
+    pub focus_candidate: Color,
+    pub focus_excluded: Color,
+    pub focus_marked: Color,
+    // See-through tints for pinned focus modes, one per pin
+    pub focus_layers: Vec<Color>,
     pub hover: Color,
     pub invalid: Color,
+    // Cells a check found wrong, the digit or the center marks
+    pub mistake: Color,
+    pub mistake_marks: Color,
+    pub note: Color,
     pub selected_hover: Color,
     pub selected: Color,
     pub starter_text: Color,
     pub text: Color,
 }
 
-#[derive(Reflect, Clone, Debug, Default)]
+impl GridTheme {
+    pub fn focus_layer(&self, layer: usize) -> Color {
+        match self.focus_layers.len() {
+            0 => Color::NONE,
+            n => self.focus_layers[layer % n],
+        }
+    }
+}
+
+#[derive(Reflect, Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
 pub struct ToolTheme {
     pub bg: Color,
     pub hover: Color,
@@ -31,31 +70,40 @@ pub struct ToolTheme {
     pub text: Color,
 }
 
+// Which of the theme's colors a node was spawned with, so it can be colored
+// again when the theme changes. Text gets it as its color, anything else as
+// its background.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ThemeColor {
+    WindowBg,
+    GridBorder,
+    GridNote,
+    GridText,
+    ToolBg,
+    ToolPanelBg,
+    ToolText,
+}
+
+impl ThemeColor {
+    pub fn get(&self, theme: &Theme) -> Color {
+        match self {
+            ThemeColor::WindowBg => theme.window_bg,
+            ThemeColor::GridBorder => theme.grid.border,
+            ThemeColor::GridNote => theme.grid.note,
+            ThemeColor::GridText => theme.grid.text,
+            ThemeColor::ToolBg => theme.tool.bg,
+            ThemeColor::ToolPanelBg => theme.tool.panel_bg,
+            ThemeColor::ToolText => theme.tool.text,
+        }
+    }
+}
+
 impl Theme {
+    // Used when no theme files can be read. Built from the shipped dark theme
+    // so the two can't drift apart.
     pub fn default_theme() -> Theme {
-        Theme {
-            window_bg: Color::rgb(0.1, 0.1, 0.1),
-            grid: GridTheme {
-                bg: Color::rgb(0.13, 0.13, 0.15),
-                border: Color::rgb(0.4, 0.3, 1.0),
-                cursor: Color::rgb(0.35, 0.15, 0.75),
-                focused: Color::rgba(0.75, 0.15, 0.15, 0.50),
-                hover: Color::rgb(0.25, 0.25, 0.25),
-                invalid: Color::rgba(0.85, 0.15, 0.15, 0.80),
-                selected_hover: Color::rgb(0.35, 0.75, 0.35),
-                selected: Color::rgb(0.35, 0.75, 0.35),
-                starter_text: Color::rgb(1.85, 0.05, 0.35),
-                text: Color::rgb(0.9, 0.9, 0.9),
-            },
-            tool: ToolTheme {
-                bg: Color::rgb(0.15, 0.15, 0.15),
-                hover: Color::rgb(0.25, 0.25, 0.25),
-                panel_bg: Color::rgb(0.14, 0.14, 0.18),
-                selected_hover: Color::rgb(0.35, 0.75, 0.35),
-                selected: Color::rgb(0.35, 0.75, 0.35),
-                text: Color::rgb(0.9, 0.9, 0.9),
-            },
-        }
+        ron::from_str(include_str!("../../assets/themes/dark.ron"))
+            .expect("the built in dark theme should parse")
     }
 }
 
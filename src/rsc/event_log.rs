@@ -0,0 +1,44 @@
+use bevy::prelude::*;
+
+use crate::evt::StateEvent;
+
+use super::game_state::{Action, GameState};
+
+// Every state event applied since `start`. Generating, loading and solving
+// depend on more than the events (randomness and files), so the log starts
+// over from the state they leave behind.
+#[derive(Resource, Clone, Default)]
+pub struct EventLog {
+    pub start: GameState,
+    pub events: Vec<StateEvent>,
+}
+
+impl EventLog {
+    pub fn new(start: GameState) -> Self {
+        Self {
+            start,
+            events: Vec::new(),
+        }
+    }
+
+    pub fn record(&mut self, event: StateEvent) {
+        self.events.push(event);
+    }
+
+    pub fn restart(&mut self, start: GameState) {
+        self.start = start;
+        self.events.clear();
+    }
+
+    pub fn replay(&self) -> GameState {
+        let mut state = self.start.clone();
+        for event in &self.events {
+            // Saving and loading would touch the save file again
+            if matches!(event, StateEvent::Action(Action::Save | Action::Load)) {
+                continue;
+            }
+            state.apply(event);
+        }
+        state
+    }
+}
@@ -1,139 +1,493 @@
 use bevy::{prelude::*, utils::HashSet};
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    core::{graph::Graph, value::Value},
-    sys::grid_update_system::GridCell,
+    core::{
+        annotation::Line,
+        focus::{box_number, FocusKind, FocusMode, PinnedFocus},
+        graph::{Graph, Mistake},
+        history::{Command, History},
+        layout::Layout,
+        puzzle::PuzzleSettings,
+        value::{from_val, to_val, Value},
+    },
+    evt::StateEvent,
 };
 
-use super::colors::Theme;
+use super::{
+    colors::{Palette, Theme},
+    save::{SaveGame, SAVE_PATH},
+};
 
 pub enum Markers {
     TL,
     TR,
     BL,
     BR,
+    T,
+    B,
+    L,
+    R,
+    M,
     Center,
 }
 
-#[derive(Reflect, Clone, Debug, Default)]
-pub enum Modifier {
-    Shift,
-    Ctrl,
-    Alt,
-    #[default]
-    None,
-}
+// Order corner marks are placed in, by the name of their slot. The corners fill
+// first, then the edges, and the middle only holds the ninth mark.
+pub const CORNER_SLOTS: [&str; 9] = ["TL", "TR", "BL", "BR", "T", "B", "L", "R", "M"];
 
-#[derive(Reflect, Clone, Debug, Default)]
-pub enum MouseState {
-    Pressed,
-    Released,
-    #[default]
-    None,
-}
+// One for each row of the focus panel
+pub const MAX_PINS: usize = 4;
 
-#[derive(Reflect, Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[derive(Reflect, Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Tools {
     Select,
     CornerMark,
     CenterMark,
     Fill,
     Erase,
+    Color,
+    Pen,
+    Stamp,
+    Note,
     #[default]
     None,
 }
 
-#[derive(Reflect, Clone, Debug, Default)]
+// What typed text goes into while a note is being edited
+#[derive(Reflect, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum NoteTarget {
+    Cell(u16),
+    Puzzle,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
 pub enum Action {
     CornerMark(Value),
     CenterMark(Value),
     Fill(Value),
+    FillCandidates,
+    Color(Value),
+    CommitLine(Line),
+    Stamp(u8),
+    EraseLines(u16),
     Erase(Value),
+    ClearMarks,
+    Restart,
     ClearSelection,
     Undo,
     Redo,
+    GotoHistory(usize),
+    PreviousBranch,
+    NextBranch,
     Solve,
+    // Starts the same puzzle over once it's been finished
+    PlayAgain,
+    // Compares the board with the solution, once, when asked
+    Check,
     Generate,
+    NewPuzzle(PuzzleSettings),
+    Save,
+    Load,
+    Replay,
     #[default]
     None,
 }
 
+impl Action {
+    // Name of the step an edit shows up as in the history panel
+    pub fn label(&self) -> String {
+        match self {
+            Action::CornerMark(value) => format!("Corner {}", value),
+            Action::CenterMark(value) => format!("Center {}", value),
+            Action::Fill(value) => format!("Fill {}", value),
+            Action::FillCandidates => "Candidates".to_string(),
+            Action::Color(value) => format!("Color {}", value),
+            Action::CommitLine(_) => "Line".to_string(),
+            Action::Stamp(n) => format!("Stamp {}", n),
+            Action::EraseLines(_) => "Erase lines".to_string(),
+            Action::Erase(_) => "Erase".to_string(),
+            Action::ClearMarks => "Clear marks".to_string(),
+            other => format!("{:?}", other),
+        }
+    }
+}
+
+// Choices made in the new puzzle dialog while it's open
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PuzzleDialog {
+    pub settings: PuzzleSettings,
+    // Generate was clicked over a game in progress and needs clicking again
+    pub confirming: bool,
+}
+
+impl PuzzleDialog {
+    // Starts from the current puzzle's choices, but with a fresh seed
+    pub fn new(current: PuzzleSettings) -> Self {
+        Self {
+            settings: PuzzleSettings {
+                seed: None,
+                ..current
+            },
+            confirming: false,
+        }
+    }
+}
+
 #[derive(Resource, Default, Clone, Debug)]
 pub struct GameState {
-    pub action: Action,
+    pub auto_eliminate: bool,
+    // Whether checking also looks for the solution missing from center marks
+    pub check_marks: bool,
+    pub color: u8,
+    // Set once every cell's filled in without conflicts, which locks the board
+    pub completed: bool,
     pub current_cell: Value,
-    pub cursor_pos: u8,
+    pub cursor_pos: u16,
+    pub drawing: Option<Line>,
+    pub editing_note: Option<NoteTarget>,
+    // Seconds spent on the puzzle, added to by the clock's ticks
+    pub elapsed: f64,
     pub entities: Vec<Entity>,
     pub focus_value: Value,
     pub graph: Graph,
-    pub graph_marked: Vec<GridCell>,
-    pub history: Vec<(Graph, Vec<GridCell>)>,
-    pub history_cursor: usize,
+    pub history: History,
+    // Times the game filled things in for the player
+    pub hints: usize,
     pub last_cell: Value,
-    pub modifier: Modifier,
-    pub mouse: MouseState,
-    pub selected_cells: HashSet<u8>,
+    pub layout: Layout,
+    // What the last check found, and how many mistakes it's found all game
+    pub mistakes: Vec<Mistake>,
+    pub mistake_count: usize,
+    // Note the current editing session has an undo step for
+    pub note_session: Option<NoteTarget>,
+    pub palette: usize,
+    // Stops the clock and hides the board
+    pub paused: bool,
+    pub pinned: Vec<PinnedFocus>,
+    // What the current puzzle was made from, with the seed it ended up using
+    pub puzzle: PuzzleSettings,
+    pub puzzle_dialog: Option<PuzzleDialog>,
+    // Index into the keymap of the binding waiting for a new key
+    pub rebinding: Option<usize>,
+    pub selected_cells: HashSet<u16>,
+    pub settings_open: bool,
+    pub stats_open: bool,
+    // Kept from when the puzzle was made or saved, nothing for one with more
+    // than one solution
+    pub solution: Option<Graph>,
     pub theme: Theme,
     pub tool: Tools,
 }
 
 impl GameState {
     pub fn new(graph: Graph) -> Self {
+        let layout = graph.layout;
         Self {
-            action: Action::None,
+            auto_eliminate: false,
+            check_marks: false,
+            color: 1,
+            completed: false,
             current_cell: Value::Unknown,
             cursor_pos: 0,
+            drawing: None,
+            editing_note: None,
+            elapsed: 0.0,
             entities: Vec::new(),
             focus_value: Value::Unknown,
             graph: graph,
-            graph_marked: Vec::new(),
-            history: Vec::new(),
-            history_cursor: 0,
+            history: History::default(),
+            hints: 0,
             last_cell: Value::Unknown,
-            modifier: Modifier::None,
-            mouse: MouseState::None,
+            layout,
+            mistakes: Vec::new(),
+            mistake_count: 0,
+            note_session: None,
+            palette: 0,
+            paused: false,
+            pinned: Vec::new(),
+            puzzle: PuzzleSettings {
+                layout,
+                ..default()
+            },
+            puzzle_dialog: None,
+            rebinding: None,
             selected_cells: HashSet::new(),
+            settings_open: false,
+            stats_open: false,
+            solution: None,
             theme: Theme::default_theme(),
             tool: Tools::Fill,
         }
     }
 
-    pub fn generate(&mut self) {
-        let new_graph = Graph::make_puzzle(40);
-        println!("Generated new graph:\n{:?}", new_graph);
-        self.graph_marked = Vec::new();
-        self.history = Vec::new();
-        self.history_cursor = 0;
-        self.graph = new_graph;
+    // Another puzzle like the current one, in whichever layout is picked now
+    pub fn next_puzzle(&self) -> PuzzleSettings {
+        PuzzleSettings {
+            layout: self.layout,
+            seed: None,
+            ..self.puzzle
+        }
+    }
+
+    pub fn start_puzzle(
+        &mut self,
+        settings: PuzzleSettings,
+        graph: Graph,
+        solution: Option<Graph>,
+    ) {
+        println!("Generated new graph:\n{:?}", graph);
+        self.history = History::default();
+        self.selected_cells = HashSet::new();
+        self.cursor_pos = 0;
+        self.editing_note = None;
+        self.puzzle_dialog = None;
+        self.mistakes = Vec::new();
+        self.mistake_count = 0;
+        self.solution = solution;
+        self.completed = false;
+        self.paused = false;
+        self.elapsed = 0.0;
+        self.hints = 0;
+        self.graph = graph;
+        self.layout = settings.layout;
+        self.puzzle = settings;
+    }
+
+    // Mistakes that were already showing from the last check aren't counted
+    // again, ones the player fixed and made again are
+    pub fn check(&mut self) {
+        let solution = match &self.solution {
+            Some(solution) => solution,
+            None => {
+                eprintln!("Puzzle doesn't have a single solution to check against");
+                return;
+            }
+        };
+        let found = self.graph.mistakes(solution, self.check_marks);
+        let graph = &self.graph;
+        let shown: Vec<Mistake> = self
+            .mistakes
+            .iter()
+            .filter(|m| m.still_in(graph))
+            .cloned()
+            .collect();
+        self.mistake_count += found.iter().filter(|m| !shown.contains(m)).count();
+        self.mistakes = found;
+    }
+
+    // Whether both agree on everything events change
+    pub fn same_game(&self, other: &GameState) -> bool {
+        self.graph == other.graph
+            && self.elapsed == other.elapsed
+            && self.history == other.history
+            && self.selected_cells == other.selected_cells
+            && self.cursor_pos == other.cursor_pos
+            && self.tool == other.tool
+            && self.color == other.color
+            && self.palette == other.palette
+            && self.focus_value == other.focus_value
+            && self.pinned == other.pinned
+            && self.mistakes == other.mistakes
+            && self.mistake_count == other.mistake_count
+            && self.hints == other.hints
+            && self.completed == other.completed
+            && self.paused == other.paused
+            && self.puzzle == other.puzzle
+            && self.auto_eliminate == other.auto_eliminate
+            && self.check_marks == other.check_marks
+            && self.editing_note == other.editing_note
+    }
+
+    // Everything that would change the board is ignored once it's finished or
+    // while it's hidden, apart from starting a game
+    pub fn locked(&self, event: &StateEvent) -> bool {
+        if !self.completed && !self.paused {
+            return false;
+        }
+        match event {
+            StateEvent::Action(
+                Action::NewPuzzle(_)
+                | Action::Generate
+                | Action::PlayAgain
+                | Action::Save
+                | Action::Load
+                | Action::Replay,
+            ) => false,
+            StateEvent::Action(_)
+            | StateEvent::Note(..)
+            | StateEvent::EditNote(_)
+            | StateEvent::Drawing(_)
+            | StateEvent::Solved(_) => true,
+            _ => false,
+        }
+    }
+
+    pub fn note(&self, target: NoteTarget) -> Option<&str> {
+        match target {
+            NoteTarget::Cell(index) => self.graph.cell(index).map(|c| c.note.as_str()),
+            NoteTarget::Puzzle => Some(&self.graph.annotations.notes),
+        }
+    }
+
+    pub fn note_mut(&mut self, target: NoteTarget) -> Option<&mut String> {
+        match target {
+            NoteTarget::Cell(index) => self.graph.index(index).map(|c| &mut c.note),
+            NoteTarget::Puzzle => Some(&mut self.graph.annotations.notes),
+        }
+    }
+
+    // Focus for clicking on `value`, which stops focusing if it's already on
+    pub fn toggle_focus(&self, value: u8) -> Value {
+        match to_val(value) {
+            value if value == self.focus_value => Value::Unknown,
+            value => value,
+        }
+    }
+
+    // What a pin button would pin right now, if there's anything to pin
+    pub fn focus_to_pin(&self, kind: FocusKind) -> Option<FocusMode> {
+        let cursor = match self.cursor_pos {
+            0 => None,
+            index => self.graph.cell(index),
+        };
+        let mode = match kind {
+            FocusKind::Digit if self.focus_value != Value::Unknown => {
+                FocusMode::Digit(from_val(self.focus_value))
+            }
+            FocusKind::Digit => FocusMode::Digit(cursor.map(|c| c.value).filter(|&v| v != 0)?),
+            FocusKind::Row => FocusMode::Row(cursor?.y),
+            FocusKind::Column => FocusMode::Column(cursor?.x),
+            FocusKind::Box => {
+                let cell = cursor?;
+                FocusMode::Box(box_number(&self.graph, cell.x, cell.y))
+            }
+            FocusKind::Peers if self.selected_cells.is_empty() => return None,
+            FocusKind::Peers => {
+                let mut cells: Vec<u16> = self.selected_cells.iter().cloned().collect();
+                cells.sort();
+                FocusMode::Peers(cells)
+            }
+        };
+        let pinned = self.pinned.iter().any(|p| p.mode == mode);
+        match pinned || self.pinned.len() < MAX_PINS {
+            true => Some(mode),
+            false => None,
+        }
+    }
+
+    pub fn palette(&self) -> &Palette {
+        &self.theme.palettes[self.palette % self.theme.palettes.len()]
+    }
+
+    pub fn save(&self) {
+        let save = SaveGame {
+            puzzle: Some(self.puzzle),
+            mistakes: self.mistake_count,
+            hints: self.hints,
+            elapsed: self.elapsed,
+            solution: self
+                .solution
+                .as_ref()
+                .map(Graph::values)
+                .unwrap_or_default(),
+            ..SaveGame::from_graph(&self.graph)
+        };
+        if let Err(e) = save.save(SAVE_PATH) {
+            eprintln!("Unable to save game: {}", e);
+        }
+    }
+
+    pub fn load(&mut self) {
+        match SaveGame::load(SAVE_PATH) {
+            Ok(save) => self.restore(&save),
+            Err(e) => eprintln!("Unable to load game: {}", e),
+        }
+    }
+
+    // Picks the game back up from a save, starting over on history
+    pub fn restore(&mut self, save: &SaveGame) {
+        self.history = History::default();
+        self.selected_cells = HashSet::new();
+        self.cursor_pos = 0;
+        self.editing_note = None;
+        self.mistakes = Vec::new();
+        self.mistake_count = save.mistakes;
+        self.paused = false;
+        self.elapsed = save.elapsed;
+        self.hints = save.hints;
+        self.graph = save.to_graph();
+        // A finished game stays finished, its clock isn't started again
+        self.completed = self.graph.is_complete();
+        // Saves from before solutions were kept have theirs worked out here
+        self.solution =
+            Graph::from_values(self.graph.layout, &save.solution).or_else(|| self.graph.solution());
+        self.layout = self.graph.layout;
+        self.puzzle = save.puzzle.unwrap_or(PuzzleSettings {
+            layout: self.layout,
+            ..default()
+        });
     }
 
-    pub fn solve(&mut self) {
-        println!("Solving Graph from: \n{:?}", self.graph);
-        self.snapshot();
-        self.graph.generate();
-        println!("Solved graph:\n{:?}", self.graph);
+    // Fills in every cell from a solved copy of the board. Marks and colors
+    // are left alone.
+    pub fn solve(&mut self, solved: &Graph) {
+        if solved.layout != self.graph.layout {
+            return;
+        }
+        println!("Solved graph:\n{:?}", solved);
+        if self.solution.is_none() {
+            self.solution = Some(solved.clone());
+        }
+        self.edit("Solve", |state| {
+            for (cell, solved) in state.graph.cells.iter_mut().zip(&solved.cells) {
+                cell.value = solved.value;
+            }
+        });
     }
 
-    pub fn snapshot(&mut self) {
-        self.history.truncate(self.history_cursor + 1);
+    // Runs an edit and records everything it changed as one undo step
+    pub fn edit(&mut self, label: &str, edit: impl FnOnce(&mut Self)) {
+        let before = self.graph.clone();
+        edit(self);
         self.history
-            .push((self.graph.clone(), self.graph_marked.clone()));
-        self.history_cursor = self.history.len() - 1;
+            .record(Command::diff(label, &before, &self.graph));
     }
 
-    pub fn undo(&mut self) {
-        if self.history_cursor > 0 {
-            self.history_cursor -= 1;
-            self.graph = self.history[self.history_cursor].0.clone();
-            self.graph_marked = self.history[self.history_cursor].1.clone();
+    // Whether the action would do anything, buttons for it are greyed out
+    // when it wouldn't
+    pub fn enabled(&self, action: &Action) -> bool {
+        if self.completed || self.paused {
+            return false;
         }
+        match action {
+            Action::Undo => self.history.can_undo(),
+            Action::Redo => self.history.can_redo(),
+            Action::Restart => self.graph.has_entries(),
+            Action::ClearMarks => self.graph.has_marks(),
+            Action::Check => self.graph.cells.iter().any(|c| {
+                c.mutable && (c.value != 0 || (self.check_marks && !c.center_marks.is_empty()))
+            }),
+            _ => true,
+        }
+    }
+
+    pub fn undo(&mut self) {
+        self.history.undo(&mut self.graph);
     }
 
     pub fn redo(&mut self) {
-        if self.history.len() > 0 && self.history_cursor < self.history.len() - 1 {
-            self.history_cursor += 1;
-            self.graph = self.history[self.history_cursor].0.clone();
-            self.graph_marked = self.history[self.history_cursor].1.clone();
+        self.history.redo(&mut self.graph);
+    }
+
+    pub fn goto_history(&mut self, node: usize) {
+        self.history.goto(node, &mut self.graph);
+    }
+
+    // Moves over to the neighbouring branch where history last split
+    pub fn switch_branch(&mut self, step: isize) {
+        if let Some(node) = self.history.sibling(step) {
+            self.goto_history(node);
         }
     }
 }
@@ -0,0 +1,132 @@
+use std::sync::{Arc, Mutex};
+
+use bevy::{
+    prelude::*,
+    tasks::{AsyncComputeTaskPool, Task},
+};
+
+use crate::core::{
+    graph::Graph,
+    puzzle::{Progress, Puzzle, PuzzleSettings},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum JobKind {
+    Puzzle(PuzzleSettings),
+    Solve,
+}
+
+pub struct Job {
+    pub kind: JobKind,
+    pub progress: Progress,
+    // Set once the work's done, to nothing if it failed or was cancelled
+    result: Arc<Mutex<Option<Option<Puzzle>>>>,
+    // Kept so the task isn't dropped, the result comes back through `result`
+    _task: Task<()>,
+}
+
+impl Job {
+    pub fn describe(&self) -> String {
+        match self.kind {
+            JobKind::Puzzle(settings) => format!(
+                "Generating {} {} puzzle... {}%",
+                settings.difficulty.name(),
+                settings.layout.name(),
+                self.progress.percent()
+            ),
+            JobKind::Solve => "Solving...".to_string(),
+        }
+    }
+
+    fn spawn(
+        kind: JobKind,
+        work: impl FnOnce(&Progress) -> Option<Puzzle> + Send + 'static,
+    ) -> Job {
+        let progress = Progress::default();
+        let result = Arc::new(Mutex::new(None));
+        let task = {
+            let (progress, result) = (progress.clone(), result.clone());
+            AsyncComputeTaskPool::get().spawn(async move {
+                let puzzle = work(&progress);
+                *result.lock().unwrap() = Some(puzzle);
+            })
+        };
+        Job {
+            kind,
+            progress,
+            result,
+            _task: task,
+        }
+    }
+
+    fn puzzle(settings: PuzzleSettings) -> Job {
+        let settings = settings.with_seed();
+        Job::spawn(JobKind::Puzzle(settings), move |progress| {
+            Graph::make_puzzle(&settings, progress)
+        })
+    }
+}
+
+// Takes the job out of `slot` along with what it made, once it's finished
+fn finished(slot: &mut Option<Job>) -> Option<(JobKind, Option<Puzzle>)> {
+    let puzzle = slot.as_ref()?.result.lock().unwrap().take()?;
+    let job = slot.take()?;
+    Some((job.kind, puzzle))
+}
+
+// Makes puzzles and solves them off the main thread so the window keeps
+// drawing. One job the player's waiting on runs at a time, starting another
+// cancels it. Puzzles for the pool are made one at a time on the side.
+#[derive(Resource, Default)]
+pub struct Generator {
+    job: Option<Job>,
+    refill: Option<Job>,
+}
+
+impl Generator {
+    pub fn job(&self) -> Option<&Job> {
+        self.job.as_ref()
+    }
+
+    pub fn busy(&self) -> bool {
+        self.job.is_some()
+    }
+
+    pub fn start_puzzle(&mut self, settings: PuzzleSettings) {
+        self.cancel();
+        self.job = Some(Job::puzzle(settings));
+    }
+
+    pub fn start_solve(&mut self, graph: &Graph) {
+        self.cancel();
+        let graph = graph.clone();
+        self.job = Some(Job::spawn(JobKind::Solve, move |progress| {
+            let solution = graph.solve(progress)?;
+            Some(Puzzle { graph, solution })
+        }));
+    }
+
+    pub fn cancel(&mut self) {
+        if let Some(job) = self.job.take() {
+            job.progress.cancel();
+        }
+    }
+
+    pub fn finished(&mut self) -> Option<(JobKind, Option<Puzzle>)> {
+        finished(&mut self.job)
+    }
+
+    // Starts on a puzzle for the pool, unless one's already being made
+    pub fn start_refill(&mut self, settings: PuzzleSettings) {
+        if self.refill.is_none() {
+            self.refill = Some(Job::puzzle(settings));
+        }
+    }
+
+    pub fn refilled(&mut self) -> Option<(PuzzleSettings, Puzzle)> {
+        match finished(&mut self.refill)? {
+            (JobKind::Puzzle(settings), Some(puzzle)) => Some((settings, puzzle)),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,26 @@
+use bevy::prelude::*;
+
+#[derive(Reflect, Clone, Debug, Default)]
+pub enum Modifier {
+    Shift,
+    Ctrl,
+    Alt,
+    #[default]
+    None,
+}
+
+#[derive(Reflect, Clone, Debug, Default)]
+pub enum MouseState {
+    Pressed,
+    Released,
+    #[default]
+    None,
+}
+
+// The mouse button and modifier keys being held. They change every frame and
+// aren't part of the game, so they're kept out of game state and the reducer.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct InputState {
+    pub modifier: Modifier,
+    pub mouse: MouseState,
+}
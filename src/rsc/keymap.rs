@@ -0,0 +1,439 @@
+use std::{fs, path::Path};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::core::value::Value;
+
+use super::game_state::{Action, Tools};
+
+pub const KEYMAP_PATH: &str = "keymap.ron";
+
+// Bumped whenever saved bindings need changing to keep doing the same thing
+const KEYMAP_VERSION: u32 = 1;
+
+// A key along with the modifiers that have to be held for it
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct KeyChord {
+    pub key: KeyCode,
+    #[serde(default)]
+    pub shift: bool,
+    #[serde(default)]
+    pub ctrl: bool,
+    #[serde(default)]
+    pub alt: bool,
+}
+
+impl KeyChord {
+    pub fn new(key: KeyCode) -> KeyChord {
+        KeyChord {
+            key,
+            shift: false,
+            ctrl: false,
+            alt: false,
+        }
+    }
+
+    pub fn ctrl(key: KeyCode) -> KeyChord {
+        KeyChord {
+            ctrl: true,
+            ..KeyChord::new(key)
+        }
+    }
+
+    pub fn alt(key: KeyCode) -> KeyChord {
+        KeyChord {
+            alt: true,
+            ..KeyChord::new(key)
+        }
+    }
+
+    // `key` with whichever modifiers are currently held
+    pub fn pressed(key: KeyCode, keyboard: &Input<KeyCode>) -> KeyChord {
+        KeyChord {
+            key,
+            shift: keyboard.any_pressed([KeyCode::LShift, KeyCode::RShift]),
+            ctrl: keyboard.any_pressed([KeyCode::LControl, KeyCode::RControl]),
+            alt: keyboard.any_pressed([KeyCode::LAlt, KeyCode::RAlt]),
+        }
+    }
+
+    pub fn is_modifier(key: KeyCode) -> bool {
+        matches!(
+            key,
+            KeyCode::LShift
+                | KeyCode::RShift
+                | KeyCode::LControl
+                | KeyCode::RControl
+                | KeyCode::LAlt
+                | KeyCode::RAlt
+                | KeyCode::LWin
+                | KeyCode::RWin
+        )
+    }
+
+    pub fn label(&self) -> String {
+        let mut label = String::new();
+        if self.ctrl {
+            label.push_str("Ctrl+");
+        }
+        if self.alt {
+            label.push_str("Alt+");
+        }
+        if self.shift {
+            label.push_str("Shift+");
+        }
+        label.push_str(&format!("{:?}", self.key));
+        label
+    }
+}
+
+// What a key chord does. Most are actions or tools, the rest change settings
+// or move the cursor without going through an action.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum Binding {
+    Action(Action),
+    Tool(Tools),
+    // Passed to the current tool. Only stamps have a tenth choice, on 0
+    Digit(u8),
+    NextTool,
+    NextLayout,
+    ToggleAutoEliminate,
+    ToggleCheckMarks,
+    TogglePause,
+    NextPalette,
+    PreviousColor,
+    NextColor,
+    CursorLeft,
+    CursorRight,
+    CursorUp,
+    CursorDown,
+    SelectAll,
+    Settings,
+    Stats,
+    NextTheme,
+    ToggleCues,
+    TextLarger,
+    TextSmaller,
+    NewPuzzle,
+    // Focus on the digit under the cursor, or stop focusing
+    FocusDigit,
+}
+
+impl Binding {
+    // Held modifiers change what these do instead of needing their own chords
+    pub fn takes_modifiers(&self) -> bool {
+        matches!(
+            self,
+            Binding::Digit(_)
+                | Binding::CursorLeft
+                | Binding::CursorRight
+                | Binding::CursorUp
+                | Binding::CursorDown
+        )
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            Binding::Action(Action::Erase(_)) => "Erase".to_string(),
+            Binding::Action(Action::FillCandidates) => "Fill candidates".to_string(),
+            Binding::Action(Action::ClearSelection) => "Clear selection".to_string(),
+            Binding::Action(Action::Check) => "Check for mistakes".to_string(),
+            Binding::Action(Action::PreviousBranch) => "Previous branch".to_string(),
+            Binding::Action(Action::NextBranch) => "Next branch".to_string(),
+            Binding::Action(action) => format!("{:?}", action),
+            Binding::Tool(tool) => format!("{:?} tool", tool),
+            Binding::Digit(n) => format!("Digit {}", n % 10),
+            Binding::NextTool => "Next tool".to_string(),
+            Binding::NextLayout => "Next layout".to_string(),
+            Binding::ToggleAutoEliminate => "Auto eliminate".to_string(),
+            Binding::ToggleCheckMarks => "Check marks too".to_string(),
+            Binding::TogglePause => "Pause".to_string(),
+            Binding::NextPalette => "Next palette".to_string(),
+            Binding::PreviousColor => "Previous color".to_string(),
+            Binding::NextColor => "Next color".to_string(),
+            Binding::CursorLeft => "Cursor left".to_string(),
+            Binding::CursorRight => "Cursor right".to_string(),
+            Binding::CursorUp => "Cursor up".to_string(),
+            Binding::CursorDown => "Cursor down".to_string(),
+            Binding::SelectAll => "Select all".to_string(),
+            Binding::Settings => "Key bindings".to_string(),
+            Binding::Stats => "Stats".to_string(),
+            Binding::NextTheme => "Next theme".to_string(),
+            Binding::ToggleCues => "Non-color cues".to_string(),
+            Binding::TextLarger => "Larger text".to_string(),
+            Binding::TextSmaller => "Smaller text".to_string(),
+            Binding::NewPuzzle => "New puzzle".to_string(),
+            Binding::FocusDigit => "Focus digit".to_string(),
+        }
+    }
+}
+
+// Keyboard shortcuts, kept as a list so a chord can end up bound twice (by
+// hand editing the file or rebinding) and be reported instead of lost.
+#[derive(Resource, Serialize, Deserialize, Clone, Debug)]
+pub struct Keymap {
+    // Files saved before there was a version count as 0
+    #[serde(default)]
+    pub version: u32,
+    pub bindings: Vec<(KeyChord, Binding)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let digits = [
+            (KeyCode::Key1, KeyCode::Numpad1),
+            (KeyCode::Key2, KeyCode::Numpad2),
+            (KeyCode::Key3, KeyCode::Numpad3),
+            (KeyCode::Key4, KeyCode::Numpad4),
+            (KeyCode::Key5, KeyCode::Numpad5),
+            (KeyCode::Key6, KeyCode::Numpad6),
+            (KeyCode::Key7, KeyCode::Numpad7),
+            (KeyCode::Key8, KeyCode::Numpad8),
+            (KeyCode::Key9, KeyCode::Numpad9),
+            (KeyCode::Key0, KeyCode::Numpad0),
+        ];
+
+        let mut bindings = vec![
+            (
+                KeyChord::new(KeyCode::Escape),
+                Binding::Action(Action::ClearSelection),
+            ),
+            (KeyChord::new(KeyCode::Tab), Binding::NewPuzzle),
+            (KeyChord::new(KeyCode::V), Binding::NextLayout),
+            (
+                KeyChord::new(KeyCode::Delete),
+                Binding::Action(Action::Erase(Value::Unknown)),
+            ),
+            (
+                KeyChord::new(KeyCode::Back),
+                Binding::Action(Action::Erase(Value::Unknown)),
+            ),
+            (
+                KeyChord::new(KeyCode::C),
+                Binding::Action(Action::FillCandidates),
+            ),
+            (KeyChord::new(KeyCode::A), Binding::ToggleAutoEliminate),
+            (KeyChord::new(KeyCode::P), Binding::NextPalette),
+            (KeyChord::new(KeyCode::LBracket), Binding::PreviousColor),
+            (KeyChord::new(KeyCode::RBracket), Binding::NextColor),
+            (
+                KeyChord::new(KeyCode::Return),
+                Binding::Action(Action::Solve),
+            ),
+            (KeyChord::new(KeyCode::Space), Binding::NextTool),
+            (KeyChord::new(KeyCode::F), Binding::Tool(Tools::Fill)),
+            (KeyChord::new(KeyCode::H), Binding::FocusDigit),
+            (KeyChord::new(KeyCode::K), Binding::Action(Action::Check)),
+            (KeyChord::new(KeyCode::Pause), Binding::TogglePause),
+            (KeyChord::ctrl(KeyCode::P), Binding::TogglePause),
+            (
+                KeyChord {
+                    shift: true,
+                    ..KeyChord::new(KeyCode::K)
+                },
+                Binding::ToggleCheckMarks,
+            ),
+            (KeyChord::ctrl(KeyCode::S), Binding::Action(Action::Save)),
+            (KeyChord::ctrl(KeyCode::O), Binding::Action(Action::Load)),
+            (KeyChord::ctrl(KeyCode::Z), Binding::Action(Action::Undo)),
+            (KeyChord::ctrl(KeyCode::Y), Binding::Action(Action::Redo)),
+            (
+                KeyChord::alt(KeyCode::Left),
+                Binding::Action(Action::PreviousBranch),
+            ),
+            (
+                KeyChord::alt(KeyCode::Right),
+                Binding::Action(Action::NextBranch),
+            ),
+            (KeyChord::new(KeyCode::Left), Binding::CursorLeft),
+            (KeyChord::new(KeyCode::Right), Binding::CursorRight),
+            (KeyChord::new(KeyCode::Up), Binding::CursorUp),
+            (KeyChord::new(KeyCode::Down), Binding::CursorDown),
+            (KeyChord::ctrl(KeyCode::A), Binding::SelectAll),
+            (KeyChord::new(KeyCode::F1), Binding::Settings),
+            (KeyChord::new(KeyCode::F2), Binding::Stats),
+            (KeyChord::new(KeyCode::F3), Binding::NextTheme),
+            (KeyChord::new(KeyCode::F4), Binding::ToggleCues),
+            (KeyChord::ctrl(KeyCode::Equals), Binding::TextLarger),
+            (KeyChord::ctrl(KeyCode::Minus), Binding::TextSmaller),
+            (KeyChord::new(KeyCode::F12), Binding::Action(Action::Replay)),
+        ];
+        for (n, (key, numpad)) in digits.into_iter().enumerate() {
+            let digit = n as u8 + 1;
+            bindings.push((KeyChord::new(key), Binding::Digit(digit)));
+            bindings.push((KeyChord::new(numpad), Binding::Digit(digit)));
+        }
+
+        Keymap {
+            version: KEYMAP_VERSION,
+            bindings,
+        }
+    }
+}
+
+impl Keymap {
+    // Digits and cursor keys ignore modifiers unless the chord is bound to
+    // something else, so Shift+Left still finds the binding for Left
+    pub fn binding_for(&self, chord: &KeyChord) -> Option<&Binding> {
+        self.bindings
+            .iter()
+            .find(|(c, _)| c == chord)
+            .or_else(|| {
+                self.bindings
+                    .iter()
+                    .find(|(c, b)| *c == KeyChord::new(chord.key) && b.takes_modifiers())
+            })
+            .map(|(_, b)| b)
+    }
+
+    // Chords bound more than once, only the first of them ever fires
+    pub fn conflicts(&self) -> Vec<KeyChord> {
+        let mut conflicts: Vec<KeyChord> = Vec::new();
+        for (i, (chord, _)) in self.bindings.iter().enumerate() {
+            if self.bindings[..i].iter().any(|(c, _)| c == chord) && !conflicts.contains(chord) {
+                conflicts.push(*chord);
+            }
+        }
+        conflicts
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let contents = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|e| e.to_string())?;
+        fs::write(path, contents).map_err(|e| e.to_string())
+    }
+
+    // Without a file the defaults are used. Saved files get whatever bindings
+    // were added since, so new shortcuts work without resetting the old ones.
+    pub fn load(path: &str) -> Result<Keymap, String> {
+        if !Path::new(path).exists() {
+            return Ok(Keymap::default());
+        }
+        let contents = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let mut keymap: Keymap = ron::from_str(&contents).map_err(|e| e.to_string())?;
+        if keymap.version > KEYMAP_VERSION {
+            return Err(format!("Unsupported keymap version {}", keymap.version));
+        }
+        keymap.upgrade();
+        Ok(keymap)
+    }
+
+    fn upgrade(&mut self) {
+        // Tab used to start a new puzzle straight away, it opens the dialog now
+        if self.version < 1 {
+            for (_, binding) in self.bindings.iter_mut() {
+                if *binding == Binding::Action(Action::Generate) {
+                    *binding = Binding::NewPuzzle;
+                }
+            }
+        }
+        self.version = KEYMAP_VERSION;
+
+        // Defaults only go on chords that are still free, so nothing saved
+        // gets shadowed and no new conflicts show up
+        for (chord, binding) in Keymap::default().bindings {
+            if self.binding_for(&chord).is_none() {
+                self.bindings.push((chord, binding));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults_have_no_conflicts() {
+        assert!(Keymap::default().conflicts().is_empty());
+    }
+
+    #[test]
+    fn conflicts() {
+        let mut keymap = Keymap::default();
+        let solve = KeyChord::new(KeyCode::Return);
+        let undo = KeyChord::ctrl(KeyCode::Z);
+        keymap.bindings.push((solve, Binding::Stats));
+        keymap.bindings.push((undo, Binding::Stats));
+        keymap.bindings.push((solve, Binding::NextTheme));
+
+        // Each chord once, in the order they were first doubled up
+        assert_eq!(keymap.conflicts(), vec![solve, undo]);
+        // The earlier binding still wins
+        assert_eq!(
+            keymap.binding_for(&solve),
+            Some(&Binding::Action(Action::Solve))
+        );
+    }
+
+    #[test]
+    fn old_files_get_new_bindings() {
+        let path = std::env::temp_dir().join("sudoku-keymap-old.ron");
+        let path = path.to_str().unwrap();
+        fs::write(
+            path,
+            "(bindings: [((key: Tab), Action(Generate)), ((key: F2), Settings)])",
+        )
+        .unwrap();
+        let keymap = Keymap::load(path);
+        let _ = fs::remove_file(path);
+        let keymap = keymap.unwrap();
+
+        assert_eq!(keymap.version, KEYMAP_VERSION);
+        assert_eq!(
+            keymap.binding_for(&KeyChord::new(KeyCode::Tab)),
+            Some(&Binding::NewPuzzle)
+        );
+        // Rebound by the player, so it isn't moved back
+        assert_eq!(
+            keymap.binding_for(&KeyChord::new(KeyCode::F2)),
+            Some(&Binding::Settings)
+        );
+        assert_eq!(
+            keymap.binding_for(&KeyChord::alt(KeyCode::Left)),
+            Some(&Binding::Action(Action::PreviousBranch))
+        );
+        // Stats' default chord is taken, so it's left for the player to bind
+        assert!(keymap.conflicts().is_empty());
+        assert!(!keymap.bindings.iter().any(|(_, b)| *b == Binding::Stats));
+    }
+
+    #[test]
+    fn old_files_get_numpad_digits() {
+        let path = std::env::temp_dir().join("sudoku-keymap-digits.ron");
+        let path = path.to_str().unwrap();
+        let digits: Vec<String> = (1..=9)
+            .map(|n| format!("((key: Key{}), Digit({}))", n, n))
+            .collect();
+        fs::write(path, format!("(bindings: [{}])", digits.join(", "))).unwrap();
+        let keymap = Keymap::load(path);
+        let _ = fs::remove_file(path);
+        let keymap = keymap.unwrap();
+
+        assert_eq!(
+            keymap.binding_for(&KeyChord::new(KeyCode::Key5)),
+            Some(&Binding::Digit(5))
+        );
+        assert_eq!(
+            keymap.binding_for(&KeyChord::new(KeyCode::Numpad5)),
+            Some(&Binding::Digit(5))
+        );
+        assert_eq!(
+            keymap.binding_for(&KeyChord::new(KeyCode::Numpad0)),
+            Some(&Binding::Digit(10))
+        );
+        assert!(keymap.conflicts().is_empty());
+    }
+
+    #[test]
+    fn bad_files_are_reported() {
+        let path = std::env::temp_dir().join("sudoku-keymap-bad.ron");
+        let path = path.to_str().unwrap();
+        fs::write(path, "(version: 1, bindings: [((key: F9), Teleport)])").unwrap();
+        let keymap = Keymap::load(path);
+        let _ = fs::remove_file(path);
+
+        assert!(keymap.is_err());
+    }
+}
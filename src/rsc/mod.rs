@@ -1,2 +1,13 @@
 pub mod colors;
+pub mod event_log;
 pub mod game_state;
+pub mod generator;
+pub mod input_state;
+pub mod keymap;
+pub mod pool;
+pub mod prefs;
+pub mod reducer;
+pub mod save;
+pub mod stats;
+pub mod themes;
+pub mod view;
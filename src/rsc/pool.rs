@@ -0,0 +1,210 @@
+use std::{fs, path::Path};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::core::{
+    graph::Graph,
+    puzzle::{Puzzle, PuzzleSettings},
+};
+
+pub const POOL_PATH: &str = "puzzles.ron";
+
+// Bumped whenever stored puzzles change shape
+const POOL_VERSION: u32 = 1;
+
+// Puzzles kept ready of each kind
+const POOL_SIZE: usize = 3;
+// How many kinds are kept, the ones asked for most recently
+const POOL_KINDS: usize = 4;
+
+// Only the givens and the solution are stored, in the same order as the
+// layout's cells
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PooledPuzzle {
+    pub settings: PuzzleSettings,
+    pub givens: Vec<u8>,
+    #[serde(default)]
+    pub solution: Vec<u8>,
+}
+
+impl PooledPuzzle {
+    // Nothing unless there's a given and a solution value for every cell,
+    // anything else is left over from another layout or an older pool
+    fn to_puzzle(&self) -> Option<Puzzle> {
+        let layout = self.settings.layout;
+        Some(Puzzle {
+            graph: Graph::from_values(layout, &self.givens)?,
+            solution: Graph::from_values(layout, &self.solution)?,
+        })
+    }
+}
+
+// Puzzles made ahead of time in the background, so starting a new one doesn't
+// have to wait. A kind is a layout, difficulty and symmetry, any seed.
+#[derive(Resource, Serialize, Deserialize, Clone, Debug)]
+pub struct PuzzlePool {
+    #[serde(default)]
+    pub version: u32,
+    pub kinds: Vec<PuzzleSettings>,
+    pub puzzles: Vec<PooledPuzzle>,
+}
+
+impl Default for PuzzlePool {
+    fn default() -> Self {
+        PuzzlePool {
+            version: POOL_VERSION,
+            kinds: Vec::new(),
+            puzzles: Vec::new(),
+        }
+    }
+}
+
+fn kind(settings: PuzzleSettings) -> PuzzleSettings {
+    PuzzleSettings {
+        seed: None,
+        ..settings
+    }
+}
+
+impl PuzzlePool {
+    // Moves the kind to the front of the ones kept, dropping the oldest
+    pub fn want(&mut self, settings: PuzzleSettings) {
+        let wanted = kind(settings);
+        self.kinds.retain(|&k| k != wanted);
+        self.kinds.insert(0, wanted);
+        self.kinds.truncate(POOL_KINDS);
+        let kinds = &self.kinds;
+        self.puzzles.retain(|p| kinds.contains(&kind(p.settings)));
+    }
+
+    // A ready made puzzle of the same kind. Settings with a seed ask for one
+    // puzzle in particular, so those are never taken from the pool.
+    pub fn take(&mut self, settings: PuzzleSettings) -> Option<(PuzzleSettings, Puzzle)> {
+        self.want(settings);
+        if settings.seed.is_some() {
+            return None;
+        }
+        let i = self
+            .puzzles
+            .iter()
+            .position(|p| kind(p.settings) == kind(settings))?;
+        let puzzle = self.puzzles.remove(i);
+        Some((puzzle.settings, puzzle.to_puzzle()?))
+    }
+
+    pub fn add(&mut self, settings: PuzzleSettings, puzzle: &Puzzle) {
+        if !self.kinds.contains(&kind(settings)) {
+            return;
+        }
+        self.puzzles.push(PooledPuzzle {
+            settings,
+            givens: puzzle
+                .graph
+                .cells
+                .iter()
+                .map(|c| if c.mutable { 0 } else { c.value })
+                .collect(),
+            solution: puzzle.solution.values(),
+        });
+    }
+
+    pub fn count(&self, settings: PuzzleSettings) -> usize {
+        self.puzzles
+            .iter()
+            .filter(|p| kind(p.settings) == kind(settings))
+            .count()
+    }
+
+    // Most recently wanted kind that's running low
+    pub fn next_refill(&self) -> Option<PuzzleSettings> {
+        self.kinds
+            .iter()
+            .find(|&&k| self.count(k) < POOL_SIZE)
+            .copied()
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let contents = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|e| e.to_string())?;
+        fs::write(path, contents).map_err(|e| e.to_string())
+    }
+
+    // Starts empty without a file. Puzzles that don't fit their layout, or
+    // were stored before solutions were, are dropped so the rest can be used.
+    pub fn load(path: &str) -> Result<PuzzlePool, String> {
+        if !Path::new(path).exists() {
+            return Ok(PuzzlePool::default());
+        }
+        let contents = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let mut pool: PuzzlePool = ron::from_str(&contents).map_err(|e| e.to_string())?;
+        if pool.version > POOL_VERSION {
+            return Err(format!("Unsupported puzzle pool version {}", pool.version));
+        }
+        let count = pool.puzzles.len();
+        pool.puzzles.retain(|p| p.to_puzzle().is_some());
+        if pool.puzzles.len() < count {
+            eprintln!(
+                "Dropped {} puzzles that don't fit their layout or have no solution from {}",
+                count - pool.puzzles.len(),
+                path
+            );
+        }
+        pool.version = POOL_VERSION;
+        Ok(pool)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{layout::Layout, puzzle::Difficulty};
+
+    fn blank(layout: Layout) -> Puzzle {
+        Puzzle {
+            graph: Graph::with_layout(layout),
+            solution: Graph::with_layout(layout),
+        }
+    }
+
+    #[test]
+    fn load_drops_puzzles_that_dont_fit() {
+        let settings = PuzzleSettings {
+            layout: Layout::Twodoku,
+            difficulty: Difficulty::Easy,
+            ..PuzzleSettings::default()
+        };
+        let mut pool = PuzzlePool::default();
+        pool.want(settings);
+        pool.add(settings, &blank(Layout::Twodoku));
+        // Givens for a classic grid stored under the wrong layout
+        pool.add(settings, &blank(Layout::Classic));
+        // Stored without its solution
+        pool.add(settings, &blank(Layout::Twodoku));
+        pool.puzzles[2].solution.clear();
+
+        let path = std::env::temp_dir().join("sudoku-pool-test.ron");
+        let path = path.to_str().unwrap();
+        pool.save(path).unwrap();
+        let mut loaded = PuzzlePool::load(path).unwrap();
+        let _ = fs::remove_file(path);
+
+        assert_eq!(loaded.count(settings), 1);
+        let (_, puzzle) = loaded.take(settings).unwrap();
+        assert!(puzzle == blank(Layout::Twodoku));
+    }
+
+    #[test]
+    fn load_rejects_newer_versions() {
+        let pool = PuzzlePool {
+            version: POOL_VERSION + 1,
+            ..PuzzlePool::default()
+        };
+        let path = std::env::temp_dir().join("sudoku-pool-version-test.ron");
+        let path = path.to_str().unwrap();
+        pool.save(path).unwrap();
+        let loaded = PuzzlePool::load(path);
+        let _ = fs::remove_file(path);
+        assert!(loaded.is_err());
+    }
+}
@@ -0,0 +1,42 @@
+use std::fs;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub const PREFS_PATH: &str = "prefs.ron";
+
+// How the game looks, kept apart from the save so it stays the same from one
+// puzzle to the next
+#[derive(Resource, Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct Prefs {
+    // Name of the theme last picked, the first one is used if it's gone
+    pub theme: String,
+    // Outlines and icons on cells so their state doesn't rely on color alone
+    pub cues: bool,
+    // How much bigger or smaller digits and marks are drawn
+    pub text_scale: f32,
+}
+
+impl Default for Prefs {
+    fn default() -> Self {
+        Self {
+            theme: String::new(),
+            cues: false,
+            text_scale: 1.0,
+        }
+    }
+}
+
+impl Prefs {
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let contents = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|e| e.to_string())?;
+        fs::write(path, contents).map_err(|e| e.to_string())
+    }
+
+    pub fn load(path: &str) -> Result<Prefs, String> {
+        let contents = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        ron::from_str(&contents).map_err(|e| e.to_string())
+    }
+}
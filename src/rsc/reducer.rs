@@ -0,0 +1,334 @@
+use crate::{
+    core::{
+        annotation::{Shape, Stamp},
+        focus::{FocusMode, PinnedFocus},
+        history::Command,
+        value::{from_val, to_val},
+    },
+    evt::{Selection, StateEvent},
+};
+
+use super::game_state::{Action, GameState, MAX_PINS};
+
+impl GameState {
+    // Applies a change sent by any of the systems. Nothing else writes game
+    // state, so replaying the same events from the same start gives the same
+    // result (apart from generating, which is random).
+    pub fn apply(&mut self, event: &StateEvent) {
+        if self.locked(event) {
+            return;
+        }
+        match event {
+            StateEvent::Action(action) => self.apply_action(action),
+            StateEvent::Tool(tool) => {
+                self.tool = *tool;
+            }
+            StateEvent::Select(Selection::Set(cells)) => {
+                self.selected_cells = cells.iter().cloned().collect();
+            }
+            StateEvent::Select(Selection::Add(cells)) => {
+                self.selected_cells.extend(cells.iter().cloned());
+            }
+            StateEvent::Select(Selection::Toggle(index)) => {
+                if !self.selected_cells.remove(index) {
+                    self.selected_cells.insert(*index);
+                }
+            }
+            StateEvent::Cursor(index) => {
+                self.cursor_pos = *index;
+                self.last_cell = self.current_cell;
+                self.current_cell = to_val(self.graph.cell(*index).map_or(0, |c| c.value));
+            }
+            StateEvent::Focus(value) => {
+                self.focus_value = *value;
+            }
+            StateEvent::Pin(mode) => self.pin(mode),
+            StateEvent::TogglePin(i) => {
+                if let Some(pin) = self.pinned.get_mut(*i) {
+                    pin.active = !pin.active;
+                }
+            }
+            StateEvent::Unpin(i) => {
+                if *i < self.pinned.len() {
+                    self.pinned.remove(*i);
+                }
+            }
+            StateEvent::Layout(layout) => {
+                self.layout = *layout;
+            }
+            StateEvent::Palette(palette) => {
+                self.palette = *palette;
+            }
+            StateEvent::Color(color) => {
+                self.color = *color;
+            }
+            // A finished puzzle's clock has already stopped
+            StateEvent::Pause(on) => {
+                self.paused = *on && !self.completed;
+            }
+            StateEvent::Tick(seconds) => {
+                if !self.completed && !self.paused {
+                    self.elapsed += seconds;
+                }
+            }
+            StateEvent::CheckMarks(on) => {
+                self.check_marks = *on;
+            }
+            StateEvent::AutoEliminate(on) => {
+                self.auto_eliminate = *on;
+            }
+            StateEvent::Generated(settings, puzzle) => {
+                let solution = Some(puzzle.solution.clone());
+                self.start_puzzle(*settings, puzzle.graph.clone(), solution);
+            }
+            StateEvent::Solved(graph) => {
+                self.hints += 1;
+                self.solve(graph);
+            }
+            StateEvent::EditNote(target) => {
+                self.editing_note = *target;
+                self.note_session = None;
+            }
+            StateEvent::Drawing(line) => {
+                self.drawing = line.clone();
+            }
+            StateEvent::Settings(open) => {
+                self.settings_open = *open;
+                self.rebinding = None;
+            }
+            StateEvent::Stats(open) => {
+                self.stats_open = *open;
+            }
+            StateEvent::Theme(theme) => {
+                self.theme = theme.as_ref().clone();
+            }
+            StateEvent::Rebind(row) => {
+                self.rebinding = *row;
+            }
+            StateEvent::PuzzleDialog(dialog) => {
+                self.puzzle_dialog = dialog.clone();
+            }
+            StateEvent::Note(target, text) => {
+                let before = self.graph.clone();
+                if let Some(note) = self.note_mut(*target) {
+                    *note = text.clone();
+                }
+                // One undo step per editing session rather than per keystroke
+                let command = Command::diff("Note", &before, &self.graph);
+                if self.note_session == Some(*target) {
+                    self.history.amend(command);
+                } else {
+                    self.history.record(command);
+                    self.note_session = Some(*target);
+                }
+            }
+        }
+
+        if !self.completed && self.graph.is_complete() {
+            self.completed = true;
+        }
+    }
+
+    // Pinning a mode that's already there turns it back on. New pins get the
+    // first layer nothing else is using, so colors don't shift around.
+    fn pin(&mut self, mode: &FocusMode) {
+        if let Some(pin) = self.pinned.iter_mut().find(|p| p.mode == *mode) {
+            pin.active = true;
+            return;
+        }
+        if self.pinned.len() >= MAX_PINS {
+            return;
+        }
+        let layer = (0..)
+            .find(|l| self.pinned.iter().all(|p| p.layer != *l))
+            .unwrap_or(0);
+        self.pinned.push(PinnedFocus {
+            mode: mode.clone(),
+            active: true,
+            layer,
+        });
+    }
+
+    fn apply_action(&mut self, action: &Action) {
+        match *action {
+            Action::ClearSelection => {
+                self.cursor_pos = 0;
+                self.selected_cells.clear();
+            }
+            Action::Undo => self.undo(),
+            Action::Redo => self.redo(),
+            Action::GotoHistory(node) => self.goto_history(node),
+            Action::PreviousBranch => self.switch_branch(-1),
+            Action::NextBranch => self.switch_branch(1),
+            Action::Save => self.save(),
+            Action::Load => self.load(),
+            // Replaying needs the event log and the rest run in the background,
+            // so the reducer system handles them. Their results come back as
+            // their own events.
+            Action::Replay
+            | Action::Generate
+            | Action::NewPuzzle(_)
+            | Action::Solve
+            | Action::None => {}
+            Action::Check => self.check(),
+            Action::PlayAgain => {
+                let mut graph = self.graph.clone();
+                graph.restart();
+                let solution = self.solution.take();
+                self.start_puzzle(self.puzzle, graph, solution);
+            }
+            _ => {
+                // Everything else edits the puzzle, and each one is a single
+                // undo step however many cells it touched
+                let label = action.label();
+                self.edit(&label, |state| state.apply_edit(action));
+            }
+        }
+    }
+
+    fn apply_edit(&mut self, action: &Action) {
+        match *action {
+            Action::Fill(value) => {
+                let cells = self.selected_cells.clone();
+                for index in cells {
+                    if let Some(cell) = self.graph.index(index).filter(|c| c.mutable) {
+                        cell.value = from_val(value);
+                        if self.auto_eliminate {
+                            self.graph.eliminate(index, from_val(value));
+                        }
+                    }
+                }
+            }
+            Action::FillCandidates => {
+                self.hints += 1;
+                self.graph.fill_candidates();
+            }
+            Action::CornerMark(value) => {
+                let cells = self.selected_cells.clone();
+                for index in cells {
+                    if let Some(cell) = self.graph.index(index).filter(|c| c.mutable) {
+                        let mark = from_val(value);
+                        if cell.corner_marks.contains(&mark) {
+                            cell.corner_marks.retain(|&n| n != mark);
+                        } else {
+                            cell.corner_marks.push(mark);
+                            cell.corner_marks.sort();
+                        }
+                    }
+                }
+            }
+            Action::CenterMark(value) => {
+                let cells = self.selected_cells.clone();
+                for index in cells {
+                    let cell = self.graph.index(index);
+                    if let Some(cell) = cell.filter(|c| c.mutable && c.value == 0) {
+                        let mark = from_val(value);
+                        if cell.center_marks.contains(&mark) {
+                            cell.center_marks.retain(|&n| n != mark);
+                        } else {
+                            cell.center_marks.push(mark);
+                            cell.center_marks.sort();
+                        }
+                    }
+                }
+            }
+            Action::Color(value) => {
+                // Colors are player annotations, so givens can be painted too
+                let cells = self.selected_cells.clone();
+                let color = from_val(value);
+                let all_colored = cells.iter().all(|&index| {
+                    self.graph
+                        .cell(index)
+                        .is_some_and(|c| c.colors.contains(&color))
+                });
+                self.color = color;
+                for index in cells {
+                    if let Some(cell) = self.graph.index(index) {
+                        if all_colored {
+                            cell.colors.retain(|&n| n != color);
+                        } else if !cell.colors.contains(&color) {
+                            cell.colors.push(color);
+                            cell.colors.sort();
+                        }
+                    }
+                }
+            }
+            Action::Stamp(n) => {
+                let cells = self.selected_cells.clone();
+                let shape = match Shape::all().get((n as usize).wrapping_sub(1)) {
+                    Some(&shape) => shape,
+                    None => return,
+                };
+                let stamp = Stamp {
+                    shape,
+                    color: self.color,
+                };
+                // Same as colors, a shape every selected cell already has is taken
+                // off all of them, otherwise it's added (or recolored) everywhere
+                let all_stamped = cells.iter().all(|&index| {
+                    self.graph
+                        .cell(index)
+                        .is_some_and(|c| c.stamps.contains(&stamp))
+                });
+                for index in cells {
+                    if let Some(cell) = self.graph.index(index) {
+                        cell.stamps.retain(|s| s.shape != shape);
+                        if !all_stamped {
+                            cell.stamps.push(stamp);
+                        }
+                    }
+                }
+            }
+            Action::CommitLine(ref line) if line.points.len() > 1 => {
+                self.graph.annotations.lines.push(line.clone());
+            }
+            Action::EraseLines(index) => {
+                if let Some(cell) = self.graph.cell(index).cloned() {
+                    self.graph
+                        .annotations
+                        .lines
+                        .retain(|l| !l.touches(cell.x, cell.y));
+                }
+            }
+            Action::Erase(_) => {
+                let cells = self.selected_cells.clone();
+                for index in cells {
+                    if let Some(cell) = self.graph.index(index).filter(|c| c.mutable) {
+                        cell.value = 0;
+                        cell.corner_marks.clear();
+                        cell.center_marks.clear();
+                    }
+                }
+            }
+            Action::ClearMarks => self.graph.clear_marks(),
+            Action::Restart => self.graph.restart(),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{graph::Graph, layout::Layout, value::Value};
+
+    #[test]
+    fn edits_skip_cells_that_arent_there() {
+        let mut state = GameState::new(Graph::with_layout(Layout::Classic));
+        state.apply(&StateEvent::Select(Selection::Set(vec![0, 1, 999])));
+        for action in [
+            Action::Fill(Value::Five),
+            Action::CornerMark(Value::One),
+            Action::CenterMark(Value::Two),
+            Action::Color(Value::Three),
+            Action::Stamp(1),
+            Action::Erase(Value::Unknown),
+        ] {
+            state.apply(&StateEvent::Action(action));
+        }
+        let cell = state.graph.cell(1).unwrap();
+        assert_eq!(cell.value, 0);
+        assert_eq!(cell.colors, vec![3]);
+        assert_eq!(cell.stamps.len(), 1);
+    }
+}
@@ -0,0 +1,182 @@
+use std::fs;
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::{
+    annotation::{Annotations, Stamp},
+    graph::Graph,
+    layout::Layout,
+    puzzle::PuzzleSettings,
+};
+
+pub const SAVE_PATH: &str = "sudoku.ron";
+
+// Bumped whenever the saved format changes in a way old files can't be read
+const SAVE_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SaveGame {
+    pub version: u32,
+    pub layout: Layout,
+    pub cells: Vec<SavedCell>,
+    #[serde(default)]
+    pub annotations: Annotations,
+    // What the puzzle was generated from, if it's known
+    #[serde(default)]
+    pub puzzle: Option<PuzzleSettings>,
+    // Found by checking against the solution
+    #[serde(default)]
+    pub mistakes: usize,
+    #[serde(default)]
+    pub hints: usize,
+    // Seconds on the clock
+    #[serde(default)]
+    pub elapsed: f64,
+    // Values of the filled in grid, empty when it isn't known
+    #[serde(default)]
+    pub solution: Vec<u8>,
+}
+
+// Neighbors are derived from the layout, so only what the player can change is stored
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct SavedCell {
+    pub x: u8,
+    pub y: u8,
+    pub value: u8,
+    pub mutable: bool,
+    #[serde(default)]
+    pub corner_marks: Vec<u8>,
+    #[serde(default)]
+    pub center_marks: Vec<u8>,
+    #[serde(default)]
+    pub colors: Vec<u8>,
+    #[serde(default)]
+    pub stamps: Vec<Stamp>,
+    #[serde(default)]
+    pub note: String,
+}
+
+impl SaveGame {
+    pub fn from_graph(graph: &Graph) -> SaveGame {
+        SaveGame {
+            version: SAVE_VERSION,
+            layout: graph.layout,
+            cells: graph
+                .cells
+                .iter()
+                .map(|c| SavedCell {
+                    x: c.x,
+                    y: c.y,
+                    value: c.value,
+                    mutable: c.mutable,
+                    corner_marks: c.corner_marks.clone(),
+                    center_marks: c.center_marks.clone(),
+                    colors: c.colors.clone(),
+                    stamps: c.stamps.clone(),
+                    note: c.note.clone(),
+                })
+                .collect(),
+            annotations: graph.annotations.clone(),
+            puzzle: None,
+            mistakes: 0,
+            hints: 0,
+            elapsed: 0.0,
+            solution: Vec::new(),
+        }
+    }
+
+    pub fn to_graph(&self) -> Graph {
+        let mut graph = Graph::with_layout(self.layout);
+        graph.annotations = self.annotations.clone();
+        for saved in &self.cells {
+            let index = graph.point_to_index(saved.x, saved.y);
+            if let Some(cell) = graph.index(index) {
+                cell.value = saved.value;
+                cell.mutable = saved.mutable;
+                cell.corner_marks = saved.corner_marks.clone();
+                cell.center_marks = saved.center_marks.clone();
+                cell.colors = saved.colors.clone();
+                cell.stamps = saved.stamps.clone();
+                cell.note = saved.note.clone();
+            }
+        }
+        graph
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let contents = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|e| e.to_string())?;
+        fs::write(path, contents).map_err(|e| e.to_string())
+    }
+
+    pub fn load(path: &str) -> Result<SaveGame, String> {
+        let contents = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let save: SaveGame = ron::from_str(&contents).map_err(|e| e.to_string())?;
+        if save.version > SAVE_VERSION {
+            return Err(format!("Unsupported save version {}", save.version));
+        }
+        Ok(save)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{
+        annotation::{Line, Shape},
+        cell::Point,
+    };
+
+    #[test]
+    fn round_trip() {
+        let mut graph = Graph::with_layout(Layout::Twodoku);
+        let first = graph.point_to_index(1, 1);
+        let shared = graph.point_to_index(8, 8);
+        let cell = graph.index(first).unwrap();
+        cell.value = 5;
+        cell.mutable = false;
+        let cell = graph.index(shared).unwrap();
+        cell.value = 3;
+        cell.corner_marks = vec![1, 2];
+        cell.center_marks = vec![4, 6];
+        cell.colors = vec![2];
+        cell.stamps = vec![Stamp {
+            shape: Shape::Triangle,
+            color: 1,
+        }];
+        cell.note = "maybe".to_string();
+        graph.annotations.lines.push(Line::new(Point::new(1, 1), 3));
+        graph.annotations.notes = "Twodoku notes".to_string();
+
+        let mut save = SaveGame::from_graph(&graph);
+        save.mistakes = 2;
+        save.hints = 1;
+        save.elapsed = 61.5;
+        save.solution = (0..graph.cells.len()).map(|i| i as u8 % 9 + 1).collect();
+        let path = std::env::temp_dir().join("sudoku-save-round-trip.ron");
+        let path = path.to_str().unwrap();
+        save.save(path).unwrap();
+        let loaded = SaveGame::load(path).unwrap();
+        let _ = fs::remove_file(path);
+
+        assert_eq!(loaded.layout, Layout::Twodoku);
+        assert_eq!(loaded.mistakes, 2);
+        assert_eq!(loaded.hints, 1);
+        assert_eq!(loaded.elapsed, 61.5);
+        assert_eq!(loaded.solution, save.solution);
+        assert!(loaded.to_graph() == graph);
+    }
+
+    #[test]
+    fn rejects_newer_versions() {
+        let mut save = SaveGame::from_graph(&Graph::with_layout(Layout::Classic));
+        save.version = SAVE_VERSION + 1;
+        let path = std::env::temp_dir().join("sudoku-save-newer.ron");
+        let path = path.to_str().unwrap();
+        save.save(path).unwrap();
+        let loaded = SaveGame::load(path);
+        let _ = fs::remove_file(path);
+
+        assert!(loaded.is_err());
+    }
+}
@@ -0,0 +1,143 @@
+use std::{fs, path::Path};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    core::{
+        layout::Layout,
+        puzzle::{Difficulty, PuzzleSettings},
+    },
+    evt::GameOverEvent,
+};
+
+pub const STATS_PATH: &str = "stats.ron";
+
+// Bumped when the format changes in a way older versions can't read. Added
+// fields don't need it, files without them get the defaults.
+const STATS_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct KindStats {
+    pub layout: Layout,
+    pub difficulty: Difficulty,
+    pub started: u32,
+    pub completed: u32,
+    // Seconds, best and summed over completed games
+    pub best_time: Option<f64>,
+    pub total_time: f64,
+    // Games completed in a row, giving up on one starts it over
+    pub streak: u32,
+    pub best_streak: u32,
+    pub hints: u32,
+    pub mistakes: u32,
+}
+
+impl KindStats {
+    pub fn average_time(&self) -> Option<f64> {
+        match self.completed {
+            0 => None,
+            n => Some(self.total_time / n as f64),
+        }
+    }
+}
+
+// Kept across games for each variant and difficulty
+#[derive(Resource, Serialize, Deserialize, Clone, Debug)]
+pub struct Stats {
+    pub version: u32,
+    #[serde(default)]
+    pub kinds: Vec<KindStats>,
+    // A file that couldn't be read fully is left alone rather than written over
+    #[serde(skip)]
+    pub read_only: bool,
+}
+
+impl Default for Stats {
+    fn default() -> Stats {
+        Stats {
+            version: STATS_VERSION,
+            kinds: Vec::new(),
+            read_only: false,
+        }
+    }
+}
+
+impl Stats {
+    fn kind_mut(&mut self, settings: PuzzleSettings) -> &mut KindStats {
+        let found = self
+            .kinds
+            .iter()
+            .position(|k| k.layout == settings.layout && k.difficulty == settings.difficulty);
+        let i = match found {
+            Some(i) => i,
+            None => {
+                self.kinds.push(KindStats {
+                    layout: settings.layout,
+                    difficulty: settings.difficulty,
+                    ..default()
+                });
+                self.kinds.len() - 1
+            }
+        };
+        &mut self.kinds[i]
+    }
+
+    pub fn start(&mut self, settings: PuzzleSettings) {
+        self.kind_mut(settings).started += 1;
+    }
+
+    pub fn finish(&mut self, game: &GameOverEvent) {
+        let kind = self.kind_mut(game.puzzle);
+        kind.hints += game.hints as u32;
+        kind.mistakes += game.mistakes as u32;
+        if !game.completed {
+            kind.streak = 0;
+            return;
+        }
+        kind.completed += 1;
+        kind.total_time += game.elapsed;
+        kind.best_time = Some(kind.best_time.map_or(game.elapsed, |t| t.min(game.elapsed)));
+        kind.streak += 1;
+        kind.best_streak = kind.best_streak.max(kind.streak);
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        if self.read_only {
+            return Err(format!("{} wasn't read fully, leaving it alone", path));
+        }
+        let contents = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|e| e.to_string())?;
+        fs::write(path, contents).map_err(|e| e.to_string())
+    }
+
+    // Stats from a newer version are shown as far as they're understood.
+    // Anything that can't be read at all gives empty stats, which are only
+    // saved when there wasn't a file to begin with.
+    pub fn load(path: &str) -> Stats {
+        let read = fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|contents| ron::from_str::<Stats>(&contents).map_err(|e| e.to_string()));
+        match read {
+            Ok(stats) if stats.version > STATS_VERSION => {
+                eprintln!("Stats are from a newer version, they won't be updated");
+                Stats {
+                    read_only: true,
+                    ..stats
+                }
+            }
+            Ok(stats) => stats,
+            Err(e) => {
+                let exists = Path::new(path).exists();
+                if exists {
+                    eprintln!("Unable to load stats: {}", e);
+                }
+                Stats {
+                    read_only: exists,
+                    ..default()
+                }
+            }
+        }
+    }
+}
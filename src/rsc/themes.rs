@@ -0,0 +1,159 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use bevy::{asset::FileAssetIo, prelude::*};
+
+use super::colors::Theme;
+
+pub const THEMES_DIR: &str = "assets/themes";
+
+// Looked for where Bevy looks for assets, so themes are found wherever the
+// game's started from
+pub fn themes_dir() -> PathBuf {
+    let root = match env::var("BEVY_ASSET_ROOT") {
+        Ok(root) => PathBuf::from(root),
+        Err(_) => FileAssetIo::get_base_path(),
+    };
+    root.join(THEMES_DIR)
+}
+
+// A theme and when the file it came from was last changed, so edits to it can
+// be picked up while the game runs
+struct ThemeFile {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    theme: Theme,
+}
+
+// Every theme in the themes folder, sorted by name. The built in dark theme
+// stands in when none of them can be read.
+#[derive(Resource)]
+pub struct Themes {
+    dir: PathBuf,
+    files: Vec<ThemeFile>,
+    // Files that didn't parse, left alone until they change again
+    broken: Vec<(PathBuf, Option<SystemTime>)>,
+    current: usize,
+}
+
+impl Themes {
+    pub fn load(dir: &Path) -> Themes {
+        let mut themes = Themes {
+            dir: dir.to_path_buf(),
+            files: vec![],
+            broken: vec![],
+            current: 0,
+        };
+        themes.refresh();
+        // Until one is picked, start on the same theme as without any files
+        themes.select(&Theme::default_theme().name);
+        themes
+    }
+
+    pub fn current(&self) -> Theme {
+        self.files
+            .get(self.current)
+            .map_or_else(Theme::default_theme, |file| file.theme.clone())
+    }
+
+    pub fn select(&mut self, name: &str) -> bool {
+        match self.files.iter().position(|file| file.theme.name == name) {
+            Some(i) => {
+                self.current = i;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn next(&mut self) {
+        if !self.files.is_empty() {
+            self.current = (self.current + 1) % self.files.len();
+        }
+    }
+
+    // Picks up theme files that were added, changed or removed since the last
+    // look. A file that stops parsing keeps its last good version, so a typo
+    // while editing one doesn't throw it away. True if the current theme
+    // changed because of it.
+    pub fn refresh(&mut self) -> bool {
+        let before = self.current();
+        let current_path = self.files.get(self.current).map(|file| file.path.clone());
+
+        let mut paths: Vec<PathBuf> = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| path.extension().is_some_and(|ext| ext == "ron"))
+                .collect(),
+            Err(_) => vec![],
+        };
+        paths.sort();
+
+        let mut old = std::mem::take(&mut self.files);
+        for path in paths {
+            let modified = fs::metadata(&path).and_then(|m| m.modified()).ok();
+            let known = old.iter().position(|file| file.path == path);
+            if let Some(i) = known.filter(|&i| old[i].modified == modified) {
+                self.files.push(old.swap_remove(i));
+                continue;
+            }
+            if self.broken.contains(&(path.clone(), modified)) {
+                if let Some(i) = known {
+                    self.files.push(old.swap_remove(i));
+                }
+                continue;
+            }
+
+            match load_theme(&path) {
+                Ok(theme) => {
+                    self.broken.retain(|(p, _)| *p != path);
+                    self.files.push(ThemeFile {
+                        path,
+                        modified,
+                        theme,
+                    });
+                }
+                Err(e) => {
+                    eprintln!("Unable to load theme {}: {}", path.display(), e);
+                    self.broken.retain(|(p, _)| *p != path);
+                    self.broken.push((path, modified));
+                    if let Some(i) = known {
+                        self.files.push(old.swap_remove(i));
+                    }
+                }
+            }
+        }
+
+        self.files.sort_by(|a, b| a.theme.name.cmp(&b.theme.name));
+        self.current = current_path
+            .and_then(|path| self.files.iter().position(|file| file.path == path))
+            .unwrap_or(0);
+        self.current() != before
+    }
+}
+
+fn load_theme(path: &Path) -> Result<Theme, String> {
+    let contents = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let theme: Theme = ron::from_str(&contents).map_err(|e| e.to_string())?;
+    // Painting picks palettes by wrapping around the list
+    if theme.palettes.is_empty() {
+        return Err("a theme needs at least one palette".to_string());
+    }
+    Ok(theme)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn starts_on_the_shipped_dark_theme() {
+        let themes = Themes::load(&themes_dir());
+        assert!(themes.files.len() > 1);
+        assert!(themes.current() == Theme::default_theme());
+    }
+}
@@ -0,0 +1,77 @@
+use bevy::prelude::*;
+
+use crate::core::layout::Layout;
+
+pub const CELL_SIZE: f32 = 100.0;
+pub const MIN_ZOOM: f32 = 0.25;
+pub const MAX_ZOOM: f32 = 2.0;
+// Digits and marks can be made bigger or smaller than the zoom alone gives
+pub const MIN_TEXT_SCALE: f32 = 0.6;
+pub const MAX_TEXT_SCALE: f32 = 1.6;
+
+// Space kept free to the left of and above the first cell
+const MARGIN: Vec2 = Vec2::new(200.0, 110.0);
+// Space kept free to the right of and below the last cell when fitting
+const END_MARGIN: f32 = 50.0;
+
+// Pan and zoom of the board. Cells are positioned from their (x, y) so
+// layouts larger than the window can be scrolled and scaled.
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct BoardView {
+    pub zoom: f32,
+    pub offset: Vec2,
+    pub text_scale: f32,
+}
+
+impl Default for BoardView {
+    fn default() -> Self {
+        Self {
+            zoom: 1.0,
+            offset: MARGIN - Vec2::splat(CELL_SIZE),
+            text_scale: 1.0,
+        }
+    }
+}
+
+impl BoardView {
+    // Largest zoom (up to 1.0) that shows the whole layout within `area`.
+    pub fn fit(layout: Layout, area: Vec2) -> Self {
+        let board = Vec2::new(layout.width() as f32, layout.height() as f32) * CELL_SIZE;
+        let room = area - MARGIN - Vec2::splat(END_MARGIN);
+        let zoom = (room.x / board.x)
+            .min(room.y / board.y)
+            .clamp(MIN_ZOOM, 1.0);
+        Self {
+            zoom,
+            offset: MARGIN - Vec2::splat(CELL_SIZE * zoom),
+            text_scale: 1.0,
+        }
+    }
+
+    pub fn cell_size(&self) -> f32 {
+        CELL_SIZE * self.zoom
+    }
+
+    // Size of a board label that's `base` at a zoom of 1.0
+    pub fn font_size(&self, base: f32) -> f32 {
+        base * self.zoom * self.text_scale
+    }
+
+    // Tenths are kept exact so stepping up and back down ends where it started
+    pub fn scale_text(&mut self, steps: i8) {
+        let scale = ((self.text_scale * 10.0).round() + steps as f32) / 10.0;
+        self.text_scale = scale.clamp(MIN_TEXT_SCALE, MAX_TEXT_SCALE);
+    }
+
+    // Top left corner of the cell at (x, y) in UI coordinates.
+    pub fn cell_position(&self, x: u8, y: u8) -> Vec2 {
+        self.offset + Vec2::new(x as f32, y as f32) * self.cell_size()
+    }
+
+    // Scales the board by `factor` while keeping the point under `anchor` still.
+    pub fn zoom_at(&mut self, factor: f32, anchor: Vec2) {
+        let zoom = (self.zoom * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+        self.offset = anchor - (anchor - self.offset) * (zoom / self.zoom);
+        self.zoom = zoom;
+    }
+}
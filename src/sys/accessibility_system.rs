@@ -0,0 +1,181 @@
+use bevy::prelude::*;
+
+use crate::{
+    core::{
+        graph::{DigitFocus, Mistake},
+        value::from_val,
+    },
+    evt::AccessibilityEvent,
+    rsc::{
+        game_state::GameState,
+        prefs::{Prefs, PREFS_PATH},
+        view::{BoardView, MAX_TEXT_SCALE, MIN_TEXT_SCALE},
+    },
+    ui::{AccessibilityButton, CueEdge, CueIcon, Location},
+};
+
+// Outline widths in pixels, the cursor's is thicker so it stands out from a
+// selection around it
+const SELECTED_OUTLINE: f32 = 3.0;
+const CURSOR_OUTLINE: f32 = 7.0;
+
+pub fn accessibility_system(
+    mut accessibility_events: EventReader<AccessibilityEvent>,
+    mut prefs: ResMut<Prefs>,
+    mut view: ResMut<BoardView>,
+) {
+    let mut changed = false;
+    for event in accessibility_events.iter() {
+        match event {
+            AccessibilityEvent::ToggleCues => prefs.cues = !prefs.cues,
+            AccessibilityEvent::TextSize(steps) => {
+                view.scale_text(*steps);
+                prefs.text_scale = view.text_scale;
+            }
+        }
+        changed = true;
+    }
+    if changed {
+        if let Err(e) = prefs.save(PREFS_PATH) {
+            eprintln!("Unable to save preferences: {}", e);
+        }
+    }
+}
+
+// The cues button stays lit while they're on, and the text size buttons grey
+// out once they can't go any further
+pub fn accessibility_button_system(
+    game_state: Res<GameState>,
+    prefs: Res<Prefs>,
+    view: Res<BoardView>,
+    mut accessibility_event: EventWriter<AccessibilityEvent>,
+    mut button_query: Query<(
+        &Interaction,
+        ChangeTrackers<Interaction>,
+        &AccessibilityButton,
+        &mut BackgroundColor,
+        &Children,
+    )>,
+    mut text_query: Query<&mut Text>,
+) {
+    for (interaction, tracker, button, mut color, children) in button_query.iter_mut() {
+        let enabled = match button {
+            AccessibilityButton::Cues => true,
+            AccessibilityButton::Smaller => view.text_scale > MIN_TEXT_SCALE,
+            AccessibilityButton::Larger => view.text_scale < MAX_TEXT_SCALE,
+        };
+        if enabled && tracker.is_changed() && *interaction == Interaction::Clicked {
+            accessibility_event.send(match button {
+                AccessibilityButton::Cues => AccessibilityEvent::ToggleCues,
+                AccessibilityButton::Smaller => AccessibilityEvent::TextSize(-1),
+                AccessibilityButton::Larger => AccessibilityEvent::TextSize(1),
+            });
+        }
+
+        let bg = match *interaction {
+            _ if !enabled => game_state.theme.tool.panel_bg,
+            Interaction::Hovered | Interaction::Clicked => game_state.theme.tool.hover,
+            Interaction::None if *button == AccessibilityButton::Cues && prefs.cues => {
+                game_state.theme.tool.selected
+            }
+            Interaction::None => game_state.theme.tool.bg,
+        };
+        if color.0 != bg {
+            *color = BackgroundColor(bg);
+        }
+
+        let mut text_color = game_state.theme.tool.text;
+        if !enabled {
+            text_color.set_a(0.4);
+        }
+        if let Ok(mut text) = text_query.get_mut(children[0]) {
+            if text.sections[0].style.color != text_color {
+                text.sections[0].style.color = text_color;
+            }
+        }
+    }
+}
+
+// Outlines the selection and the cursor, and puts icons on clashing, wrong and
+// focused digits, so none of those states rely on telling colors apart. Only
+// ever shows anything with cues turned on.
+pub fn cue_system(
+    game_state: Res<GameState>,
+    prefs: Res<Prefs>,
+    mut edge_query: Query<(&CueEdge, &Location, &mut Style, &mut BackgroundColor)>,
+    mut icon_query: Query<(&Location, &mut Text), With<CueIcon>>,
+) {
+    let outline = |index: u16| {
+        if !prefs.cues {
+            None
+        } else if game_state.cursor_pos == index {
+            Some(CURSOR_OUTLINE)
+        } else if game_state.selected_cells.contains(&index) {
+            Some(SELECTED_OUTLINE)
+        } else {
+            None
+        }
+    };
+    let color = game_state.theme.grid.text;
+
+    for (edge, loc, mut style, mut bg) in edge_query.iter_mut() {
+        let display = match outline(loc.0) {
+            Some(width) => {
+                let size = match edge {
+                    CueEdge::Top | CueEdge::Bottom => {
+                        Size::new(Val::Percent(100.0), Val::Px(width))
+                    }
+                    CueEdge::Left | CueEdge::Right => {
+                        Size::new(Val::Px(width), Val::Percent(100.0))
+                    }
+                };
+                if style.size != size {
+                    style.size = size;
+                }
+                if bg.0 != color {
+                    *bg = BackgroundColor(color);
+                }
+                Display::Flex
+            }
+            None => Display::None,
+        };
+        if style.display != display {
+            style.display = display;
+        }
+    }
+
+    let (invalid, mistakes) = match prefs.cues {
+        true => (
+            game_state.graph.invalid_cells(),
+            game_state
+                .mistakes
+                .iter()
+                .filter(|m| matches!(m, Mistake::Digit(..)) && m.still_in(&game_state.graph))
+                .map(|m| m.index())
+                .collect(),
+        ),
+        false => (vec![], vec![]),
+    };
+    let focus_digit = from_val(game_state.focus_value);
+
+    for (loc, mut text) in icon_query.iter_mut() {
+        let mut icons = String::new();
+        if prefs.cues {
+            if invalid.contains(&loc.0) {
+                icons.push('!');
+            }
+            if mistakes.contains(&loc.0) {
+                icons.push('×');
+            }
+            if game_state.graph.digit_focus(loc.0, focus_digit) == Some(DigitFocus::Placed) {
+                icons.push('•');
+            }
+        }
+        if text.sections[0].value != icons {
+            text.sections[0].value = icons;
+        }
+        if text.sections[0].style.color != color {
+            text.sections[0].style.color = color;
+        }
+    }
+}
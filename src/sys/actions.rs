@@ -1,84 +1,201 @@
-use std::collections::HashSet;
-
-use bevy::prelude::*;
-use bevy_inspector_egui::egui::plot::Corner;
+use bevy::{ecs::system::SystemParam, prelude::*};
 
 use crate::{
-    core::value::from_val,
-    evt::CornerMarkEvent,
-    rsc::game_state::{Action, GameState},
+    core::puzzle::PuzzleSettings,
+    evt::{
+        CornerMarkEvent, GameOverEvent, GameStartedEvent, LayoutChangedEvent, StateEvent,
+        ThemeChangedEvent, ToolSelectedEvent,
+    },
+    rsc::{
+        event_log::EventLog,
+        game_state::{Action, GameState},
+        generator::Generator,
+        pool::{PuzzlePool, POOL_PATH},
+    },
 };
 
 use super::grid_update_system::GridCell;
-fn remove_duplicates(vector: &mut Vec<u8>) {
-    let set: HashSet<_> = vector.drain(..).collect();
-    vector.extend(set);
+
+// What the reducer tells the views about once a change has been applied
+#[derive(SystemParam)]
+pub struct ReducerEvents<'w, 's> {
+    tool_selected: EventWriter<'w, 's, ToolSelectedEvent>,
+    corner_mark: EventWriter<'w, 's, CornerMarkEvent>,
+    layout_changed: EventWriter<'w, 's, LayoutChangedEvent>,
+    game_started: EventWriter<'w, 's, GameStartedEvent>,
+    game_over: EventWriter<'w, 's, GameOverEvent>,
+    theme_changed: EventWriter<'w, 's, ThemeChangedEvent>,
 }
 
-pub fn action_system(
+// The single place state events are applied. Each one is logged, then the
+// views that react to a change are told about it.
+pub fn reducer_system(
     mut game_state: ResMut<GameState>,
-    mut corner_mark_event: EventWriter<CornerMarkEvent>,
+    mut log: ResMut<EventLog>,
+    mut generator: ResMut<Generator>,
+    mut pool: ResMut<PuzzlePool>,
+    mut state_events: EventReader<StateEvent>,
+    mut events: ReducerEvents,
     mut cell_query: Query<&mut GridCell>,
 ) {
-    match game_state.action {
-        Action::ClearSelection => {
-            game_state.action = Action::None;
-            println!("Clearing selection");
-            for mut cell in cell_query.iter_mut() {
-                cell.selected = false;
-                cell.focused = false;
-                cell.hovered = false;
+    for event in state_events.iter() {
+        // These need more than game state, so they're handled here instead of
+        // being applied. A new puzzle from the pool stands in for asking for one.
+        let event = match event {
+            StateEvent::Action(Action::Replay) => {
+                let replayed = log.replay();
+                if !replayed.same_game(&game_state) {
+                    eprintln!(
+                        "Replaying {} events gave a different state",
+                        log.events.len()
+                    );
+                }
+                continue;
             }
-        }
-        Action::Fill(value) => {
-            game_state.action = Action::None;
-            let cells = game_state.selected_cells.clone();
-            for index in cells {
-                if cell_query
-                    .iter()
-                    .find(|c| c.index == index)
-                    .unwrap()
-                    .mutable
-                {
-                    game_state.snapshot();
-                    game_state.graph.index(index.clone()).unwrap().value = from_val(value);
+            StateEvent::Action(action @ (Action::Generate | Action::NewPuzzle(_))) => {
+                let settings = match action {
+                    Action::NewPuzzle(settings) => *settings,
+                    _ => game_state.next_puzzle(),
+                };
+                match take_puzzle(&mut pool, settings) {
+                    Some(generated) => {
+                        generator.cancel();
+                        generated
+                    }
+                    None => {
+                        generator.start_puzzle(settings);
+                        continue;
+                    }
                 }
             }
-        }
-        Action::CornerMark(value) => {
-            game_state.action = Action::None;
-            let cells = game_state.selected_cells.clone();
-            for index in cells {
-                let mut cell = cell_query.iter_mut().find(|c| c.index == index).unwrap();
-                if cell.mutable {
-                    cell.corner_marks.push(from_val(value));
-                    remove_duplicates(&mut cell.corner_marks);
+            StateEvent::Action(Action::Solve) => {
+                generator.start_solve(&game_state.graph);
+                continue;
+            }
+            event => event.clone(),
+        };
+        let event = &event;
+
+        let layout = game_state.graph.layout;
+        let was_completed = game_state.completed;
+        let unfinished = !was_completed && game_state.graph.has_entries();
+        let previous = game_over(&game_state, false);
+        game_state.apply(event);
+        log.record(event.clone());
 
-                    game_state.snapshot();
-                    game_state.graph_marked.push(cell.clone());
-                    corner_mark_event.send(CornerMarkEvent(index, value));
+        match event {
+            StateEvent::Tool(tool) => {
+                events.tool_selected.send(ToolSelectedEvent(*tool));
+            }
+            StateEvent::Theme(_) => {
+                events.theme_changed.send(ThemeChangedEvent);
+            }
+            StateEvent::Action(Action::CornerMark(value)) => {
+                for &index in game_state.selected_cells.iter() {
+                    if game_state.graph.cell(index).is_some_and(|c| c.mutable) {
+                        events.corner_mark.send(CornerMarkEvent(index, *value));
+                    }
                 }
             }
-        }
-        Action::Undo => {
-            game_state.action = Action::None;
-            game_state.undo();
-        }
-        Action::Redo => {
-            game_state.action = Action::None;
-            game_state.redo();
+            StateEvent::Action(Action::ClearSelection) => {
+                for mut cell in cell_query.iter_mut() {
+                    cell.selected = false;
+                    cell.hovered = false;
+                }
+            }
+            StateEvent::Generated(..) | StateEvent::Action(Action::PlayAgain) => {
+                // Moving on from a game that was played but not finished
+                // counts as giving up on it
+                if unfinished {
+                    events.game_over.send(previous);
+                }
+                events
+                    .game_started
+                    .send(GameStartedEvent(game_state.puzzle));
+                log.restart(game_state.clone());
+            }
+            StateEvent::Action(Action::Load) => {
+                log.restart(game_state.clone());
+            }
+            _ => {}
         }
 
-        Action::Generate => {
-            game_state.action = Action::None;
-            game_state.generate();
+        // Loading a finished game or having it solved for you doesn't count
+        let finished = !was_completed && game_state.completed;
+        match event {
+            StateEvent::Action(Action::Load) => {}
+            StateEvent::Solved(_) if finished => {
+                events.game_over.send(game_over(&game_state, false));
+            }
+            _ if finished => events.game_over.send(game_over(&game_state, true)),
+            _ => {}
         }
 
-        Action::Solve => {
-            game_state.action = Action::None;
-            game_state.solve();
+        if game_state.graph.layout != layout {
+            events
+                .layout_changed
+                .send(LayoutChangedEvent(game_state.graph.layout));
         }
+    }
+}
+
+fn game_over(game_state: &GameState, completed: bool) -> GameOverEvent {
+    GameOverEvent {
+        puzzle: game_state.puzzle,
+        elapsed: game_state.elapsed,
+        hints: game_state.hints,
+        mistakes: game_state.mistake_count,
+        completed,
+    }
+}
+
+fn take_puzzle(pool: &mut PuzzlePool, settings: PuzzleSettings) -> Option<StateEvent> {
+    let (settings, puzzle) = pool.take(settings)?;
+    if let Err(e) = pool.save(POOL_PATH) {
+        eprintln!("Unable to save puzzle pool: {}", e);
+    }
+    Some(StateEvent::Generated(settings, Box::new(puzzle)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        core::{graph::Graph, puzzle::Progress},
+        evt::{Selection, ThemeChangedEvent},
+        rsc::save::SaveGame,
+    };
+
+    #[test]
+    fn restored_finished_games_arent_counted_again() {
+        let settings = PuzzleSettings {
+            seed: Some(1),
+            ..default()
+        };
+        let made = Graph::make_puzzle(&settings, &Progress::default()).unwrap();
+        let save = SaveGame::from_graph(&made.solution);
+        let mut game_state = GameState::new(save.to_graph());
+        game_state.restore(&save);
+        assert!(game_state.completed);
+
+        let mut app = App::new();
+        app.add_event::<StateEvent>()
+            .add_event::<ToolSelectedEvent>()
+            .add_event::<CornerMarkEvent>()
+            .add_event::<LayoutChangedEvent>()
+            .add_event::<GameStartedEvent>()
+            .add_event::<GameOverEvent>()
+            .add_event::<ThemeChangedEvent>()
+            .insert_resource(EventLog::new(game_state.clone()))
+            .insert_resource(game_state)
+            .init_resource::<Generator>()
+            .init_resource::<PuzzlePool>()
+            .add_system(reducer_system);
+        app.world
+            .send_event(StateEvent::Select(Selection::Set(vec![1])));
+        app.update();
 
-        _ => {}
+        assert!(app.world.resource::<GameState>().completed);
+        assert!(app.world.resource::<Events<GameOverEvent>>().is_empty());
     }
 }
@@ -0,0 +1,208 @@
+use bevy::{prelude::*, ui::FocusPolicy};
+
+use crate::{
+    core::{annotation::Line, cell::Point, graph::Graph},
+    evt::StateEvent,
+    rsc::{
+        game_state::{Action, GameState, Tools},
+        view::BoardView,
+    },
+    ui::Board,
+};
+
+// How close to a corner (as a fraction of a cell) a line has to start to
+// follow cell edges instead of cell centers
+const CORNER_SNAP: f32 = 0.2;
+
+#[derive(Component)]
+pub struct LineDot;
+
+// Cursor position in cells, where the top left corner of the board is (1, 1)
+fn board_cursor(windows: &Windows, view: &BoardView) -> Option<Vec2> {
+    let window = windows.get_primary()?;
+    let cursor = window.cursor_position()?;
+    let ui = Vec2::new(cursor.x, window.height() - cursor.y);
+    Some((ui - view.offset) / view.cell_size())
+}
+
+// Line point in half cells to UI coordinates
+fn point_position(view: &BoardView, p: Point) -> Vec2 {
+    view.offset + (Vec2::new(p.x as f32, p.y as f32) / 2.0 + 1.0) * view.cell_size()
+}
+
+fn center_point(graph: &Graph, cursor: Vec2) -> Option<Point> {
+    let (x, y) = (cursor.x.floor(), cursor.y.floor());
+    if x < 1.0 || y < 1.0 {
+        return None;
+    }
+    graph.at(x as u8, y as u8)?;
+    Some(Point::new(2 * x as u8 - 1, 2 * y as u8 - 1))
+}
+
+fn corner_point(graph: &Graph, cursor: Vec2) -> Option<Point> {
+    let (x, y) = (cursor.x.round(), cursor.y.round());
+    if x < 1.0 || y < 1.0 {
+        return None;
+    }
+    let (x, y) = (x as u8, y as u8);
+    // A corner is on the board when any of the four cells around it is
+    let on_board = [(x, y), (x - 1, y), (x, y - 1), (x - 1, y - 1)]
+        .iter()
+        .any(|&(x, y)| x > 0 && y > 0 && graph.at(x, y).is_some());
+    if !on_board {
+        return None;
+    }
+    Some(Point::new(2 * x - 2, 2 * y - 2))
+}
+
+fn near_corner(cursor: Vec2) -> bool {
+    let fraction = cursor - cursor.floor();
+    let distance = fraction.min(Vec2::ONE - fraction);
+    distance.x < CORNER_SNAP && distance.y < CORNER_SNAP
+}
+
+pub fn pen_system(
+    windows: Res<Windows>,
+    buttons: Res<Input<MouseButton>>,
+    view: Res<BoardView>,
+    game_state: Res<GameState>,
+    mut state_events: EventWriter<StateEvent>,
+) {
+    if game_state.tool != Tools::Pen {
+        return;
+    }
+
+    let cursor = match board_cursor(&windows, &view) {
+        Some(cursor) => cursor,
+        None => return,
+    };
+
+    // Worked on here and sent whenever it changes, the reducer only sees it
+    // next frame
+    let mut drawing = game_state.drawing.clone();
+    if buttons.just_pressed(MouseButton::Left) {
+        let start = if near_corner(cursor) {
+            corner_point(&game_state.graph, cursor)
+        } else {
+            center_point(&game_state.graph, cursor)
+        };
+        drawing = start.map(|p| Line::new(p, game_state.color));
+    }
+
+    if buttons.pressed(MouseButton::Left) {
+        if let Some(line) = drawing.as_mut() {
+            let target = if line.points[0].x % 2 == 0 {
+                corner_point(&game_state.graph, cursor)
+            } else {
+                center_point(&game_state.graph, cursor)
+            };
+            if let Some(target) = target {
+                if line.points.last() != Some(&target) {
+                    line.extend_to(target);
+                }
+            }
+        }
+    }
+
+    if buttons.just_released(MouseButton::Left) {
+        if let Some(line) = drawing.take() {
+            state_events.send(StateEvent::Action(Action::CommitLine(line)));
+        }
+    }
+    if drawing != game_state.drawing {
+        state_events.send(StateEvent::Drawing(drawing));
+    }
+
+    if buttons.just_pressed(MouseButton::Right) {
+        let (x, y) = (cursor.x.floor(), cursor.y.floor());
+        if x >= 1.0 && y >= 1.0 && game_state.graph.at(x as u8, y as u8).is_some() {
+            let index = game_state.graph.point_to_index(x as u8, y as u8);
+            state_events.send(StateEvent::Action(Action::EraseLines(index)));
+        }
+    }
+}
+
+// What the dots on screen were last drawn from
+#[derive(PartialEq)]
+pub struct DrawnLines {
+    // Board node the dots are children of, a new one means the board was rebuilt
+    board: Entity,
+    // Finished lines plus the one being drawn, if any
+    lines: Vec<Line>,
+    // View zoom and offset the dot positions were worked out with
+    zoom: f32,
+    offset: Vec2,
+    // Palette the line colors were picked from
+    palette: usize,
+}
+
+// UI nodes can't be rotated, so lines are drawn as rows of small dots. They're
+// only rebuilt when the lines or the view change.
+pub fn line_render_system(
+    mut cmd: Commands,
+    game_state: Res<GameState>,
+    view: Res<BoardView>,
+    board_query: Query<Entity, With<Board>>,
+    dot_query: Query<Entity, With<LineDot>>,
+    mut drawn: Local<Option<DrawnLines>>,
+) {
+    let board = match board_query.iter().next() {
+        Some(board) => board,
+        None => return,
+    };
+
+    let mut lines = game_state.graph.annotations.lines.clone();
+    lines.extend(game_state.drawing.clone());
+    let current = DrawnLines {
+        board,
+        lines,
+        zoom: view.zoom,
+        offset: view.offset,
+        palette: game_state.palette,
+    };
+    if drawn.as_ref() == Some(&current) {
+        return;
+    }
+
+    for dot in dot_query.iter() {
+        cmd.entity(dot).despawn_recursive();
+    }
+
+    let size = (view.cell_size() * 0.08).max(3.0);
+    let spacing = size * 0.75;
+    let palette = game_state.palette();
+    cmd.entity(board).with_children(|parent| {
+        for line in &current.lines {
+            let color = palette.color(line.color);
+            for pair in line.points.windows(2) {
+                let from = point_position(&view, pair[0]);
+                let to = point_position(&view, pair[1]);
+                let steps = (from.distance(to) / spacing).ceil().max(1.0) as usize;
+                for i in 0..=steps {
+                    let p = from.lerp(to, i as f32 / steps as f32) - size / 2.0;
+                    parent.spawn((
+                        NodeBundle {
+                            style: Style {
+                                size: Size::new(Val::Px(size), Val::Px(size)),
+                                position_type: PositionType::Absolute,
+                                position: UiRect {
+                                    left: Val::Px(p.x),
+                                    top: Val::Px(p.y),
+                                    ..default()
+                                },
+                                ..default()
+                            },
+                            background_color: color.into(),
+                            focus_policy: FocusPolicy::Pass,
+                            z_index: ZIndex::Global(2),
+                            ..default()
+                        },
+                        LineDot,
+                    ));
+                }
+            }
+        }
+    });
+
+    *drawn = Some(current);
+}
@@ -0,0 +1,99 @@
+use bevy::{
+    input::mouse::{MouseMotion, MouseScrollUnit, MouseWheel},
+    prelude::*,
+};
+
+use crate::{
+    evt::LayoutChangedEvent,
+    rsc::{game_state::GameState, view::BoardView},
+    ui::{spawn_board, Board, FontScale, TOOL_PANEL_WIDTH},
+};
+
+use super::grid_update_system::GridCell;
+
+// Mouse wheel zooms around the cursor, dragging with the middle button pans.
+pub fn board_zoom_system(
+    windows: Res<Windows>,
+    buttons: Res<Input<MouseButton>>,
+    mut wheel_events: EventReader<MouseWheel>,
+    mut motion_events: EventReader<MouseMotion>,
+    mut view: ResMut<BoardView>,
+) {
+    for event in wheel_events.iter() {
+        let lines = match event.unit {
+            MouseScrollUnit::Line => event.y,
+            MouseScrollUnit::Pixel => event.y / 100.0,
+        };
+
+        // Window coordinates start at the bottom left, UI coordinates at the top left
+        let cursor = windows.get_primary().and_then(|window| {
+            window
+                .cursor_position()
+                .map(|p| Vec2::new(p.x, window.height() - p.y))
+        });
+
+        if let Some(cursor) = cursor {
+            view.zoom_at(1.1_f32.powf(lines), cursor);
+        }
+    }
+
+    let pan: Vec2 = motion_events.iter().map(|event| event.delta).sum();
+    if buttons.pressed(MouseButton::Middle) && pan != Vec2::ZERO {
+        view.offset += pan;
+    }
+}
+
+pub fn board_view_system(
+    view: Res<BoardView>,
+    mut cell_query: Query<(&GridCell, &mut Style)>,
+    mut text_query: Query<(&FontScale, &mut Text)>,
+) {
+    if !view.is_changed() {
+        return;
+    }
+
+    let size = view.cell_size();
+    for (cell, mut style) in cell_query.iter_mut() {
+        let position = view.cell_position(cell.x, cell.y);
+        style.size = Size::new(Val::Px(size), Val::Px(size));
+        style.min_size = Size::new(Val::Px(size), Val::Px(size));
+        style.position.left = Val::Px(position.x);
+        style.position.top = Val::Px(position.y);
+    }
+
+    for (scale, mut text) in text_query.iter_mut() {
+        for section in text.sections.iter_mut() {
+            section.style.font_size = view.font_size(scale.0);
+        }
+    }
+}
+
+// The board is spawned per layout, so a new layout replaces every cell.
+pub fn board_rebuild_system(
+    mut cmd: Commands,
+    mut layout_changed_event: EventReader<LayoutChangedEvent>,
+    game_state: Res<GameState>,
+    asset_server: Res<AssetServer>,
+    windows: Res<Windows>,
+    mut view: ResMut<BoardView>,
+    board_query: Query<Entity, With<Board>>,
+) {
+    let layout = match layout_changed_event.iter().last() {
+        Some(LayoutChangedEvent(layout)) => *layout,
+        None => return,
+    };
+
+    for board in board_query.iter() {
+        cmd.entity(board).despawn_recursive();
+    }
+
+    if let Some(window) = windows.get_primary() {
+        let area = Vec2::new(window.width() - TOOL_PANEL_WIDTH, window.height());
+        *view = BoardView {
+            text_scale: view.text_scale,
+            ..BoardView::fit(layout, area)
+        };
+    }
+
+    spawn_board(&mut cmd, &game_state, &asset_server, &view);
+}
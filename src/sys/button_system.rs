@@ -1,17 +1,20 @@
 use bevy::prelude::*;
 
 use crate::{
-    core::value::{to_val, Value},
-    evt::FocusModeEvent,
-    rsc::game_state::{GameState, Modifier, MouseState},
+    evt::{Selection, StateEvent},
+    rsc::{
+        game_state::{GameState, NoteTarget, Tools},
+        input_state::{InputState, Modifier, MouseState},
+    },
     ui::GridButton,
 };
 
 use super::grid_update_system::GridCell;
 
 pub fn button_system(
-    mut game_state: ResMut<GameState>,
-    mut focus_mode_event: EventWriter<FocusModeEvent>,
+    game_state: Res<GameState>,
+    mut input: ResMut<InputState>,
+    mut state_events: EventWriter<StateEvent>,
     mut interaction_query: Query<
         (&Interaction, &Parent),
         (Changed<Interaction>, With<Button>, With<GridButton>),
@@ -19,46 +22,37 @@ pub fn button_system(
     mut cell_query: Query<&mut GridCell>,
 ) {
     for (interaction, parent) in &mut interaction_query {
+        // The pen draws over cells instead of selecting them
+        let drawing = game_state.tool == Tools::Pen;
         match *interaction {
+            Interaction::Clicked if drawing => {}
             Interaction::Clicked => {
                 let cell = cell_query.get_mut(parent.get()).unwrap();
-                game_state.mouse = MouseState::Pressed;
+                input.mouse = MouseState::Pressed;
 
-                if game_state.selected_cells.contains(&cell.index) {
-                    if game_state.focus_value == to_val(cell.value) {
-                        game_state.focus_value = Value::Unknown;
-                    } else {
-                        game_state.focus_value = to_val(cell.value);
-                        focus_mode_event.send(FocusModeEvent(game_state.focus_value));
-                    }
-                } else {
-                    game_state.focus_value = Value::Unknown;
+                // Filled cells toggle focus on their digit, empty ones leave
+                // it be so the digit can be placed where it's shown to fit
+                if cell.value != 0 {
+                    state_events.send(StateEvent::Focus(game_state.toggle_focus(cell.value)));
                 }
 
-                game_state.last_cell = game_state.current_cell;
-                game_state.current_cell = to_val(cell.value);
-
-                let cell = cell_query.get_mut(parent.get()).unwrap();
-                game_state.selected_cells.insert(cell.index);
-
-                match game_state.modifier {
-                    Modifier::Shift => {}
-                    _ => {
-                        let cell_index = cell.index;
-                        for c in cell_query.iter_mut() {
-                            if c.index == cell_index {
-                                continue;
-                            } else {
-                                game_state.selected_cells.retain(|&x| x != c.index);
-                            }
-                        }
-                    }
+                state_events.send(StateEvent::Cursor(cell.index));
+                if game_state.tool == Tools::Note {
+                    state_events.send(StateEvent::EditNote(Some(NoteTarget::Cell(cell.index))));
                 }
+
+                state_events.send(StateEvent::Select(match input.modifier {
+                    Modifier::Shift => Selection::Add(vec![cell.index]),
+                    // Ctrl toggles the clicked cell in or out of the selection
+                    Modifier::Ctrl => Selection::Toggle(cell.index),
+                    _ => Selection::Set(vec![cell.index]),
+                }));
             }
-            Interaction::Hovered => match game_state.mouse {
+            Interaction::Hovered => match input.mouse {
+                MouseState::Pressed if drawing => {}
                 MouseState::Pressed => {
                     let cell = cell_query.get_mut(parent.get()).unwrap();
-                    game_state.selected_cells.insert(cell.index);
+                    state_events.send(StateEvent::Select(Selection::Add(vec![cell.index])));
                 }
                 MouseState::Released => {
                     let mut cell = cell_query.get_mut(parent.get()).unwrap();
@@ -0,0 +1,107 @@
+use bevy::{prelude::*, window::WindowFocused};
+
+use crate::{
+    evt::StateEvent,
+    rsc::game_state::GameState,
+    ui::{spawn_pause_screen, ClockButton, PauseScreen, ResumeButton},
+};
+
+// Minutes and seconds, with hours in front once there are any
+pub fn format_time(seconds: f64) -> String {
+    let seconds = seconds as u64;
+    match seconds / 3600 {
+        0 => format!("{}:{:02}", seconds / 60, seconds % 60),
+        hours => format!("{}:{:02}:{:02}", hours, seconds / 60 % 60, seconds % 60),
+    }
+}
+
+// Stops for good once the puzzle's finished. Switching to another window
+// pauses the game, it's up to the player to come back to it.
+pub fn clock_system(
+    time: Res<Time>,
+    game_state: Res<GameState>,
+    mut focus_events: EventReader<WindowFocused>,
+    mut state_events: EventWriter<StateEvent>,
+) {
+    if focus_events.iter().any(|e| !e.focused) && !game_state.paused {
+        state_events.send(StateEvent::Pause(true));
+    }
+    if !game_state.completed && !game_state.paused {
+        state_events.send(StateEvent::Tick(time.delta_seconds_f64()));
+    }
+}
+
+type ClockButtonItem = (
+    &'static Interaction,
+    ChangeTrackers<Interaction>,
+    &'static mut BackgroundColor,
+    &'static Children,
+);
+
+// Shows the time, clicking it pauses or resumes
+pub fn clock_button_system(
+    game_state: Res<GameState>,
+    mut state_events: EventWriter<StateEvent>,
+    mut button_query: Query<ClockButtonItem, With<ClockButton>>,
+    mut text_query: Query<&mut Text>,
+) {
+    for (interaction, tracker, mut color, children) in button_query.iter_mut() {
+        if tracker.is_changed() && *interaction == Interaction::Clicked {
+            state_events.send(StateEvent::Pause(!game_state.paused));
+        }
+
+        let bg = match *interaction {
+            _ if game_state.paused => game_state.theme.tool.selected,
+            Interaction::Hovered | Interaction::Clicked => game_state.theme.tool.hover,
+            Interaction::None => game_state.theme.tool.bg,
+        };
+        if color.0 != bg {
+            *color = BackgroundColor(bg);
+        }
+
+        let time = format_time(game_state.elapsed);
+        let state = match (game_state.completed, game_state.paused) {
+            (true, _) => "\nSolved",
+            (false, true) => "\nResume",
+            (false, false) => "\nPause",
+        };
+        if let Ok(mut text) = text_query.get_mut(children[0]) {
+            if text.sections[0].value != time {
+                text.sections[0].value = time;
+            }
+            if text.sections[1].value != state {
+                text.sections[1].value = state.to_string();
+            }
+        }
+    }
+}
+
+pub fn pause_screen_system(
+    mut cmd: Commands,
+    game_state: Res<GameState>,
+    asset_server: Res<AssetServer>,
+    screen_query: Query<Entity, With<PauseScreen>>,
+) {
+    let screen = screen_query.iter().next();
+    match (game_state.paused, screen) {
+        (true, None) => spawn_pause_screen(&mut cmd, &game_state, &asset_server),
+        (false, Some(screen)) => cmd.entity(screen).despawn_recursive(),
+        _ => {}
+    }
+}
+
+type ResumeButtonFilter = (Changed<Interaction>, With<ResumeButton>);
+
+pub fn resume_button_system(
+    game_state: Res<GameState>,
+    mut state_events: EventWriter<StateEvent>,
+    mut button_query: Query<(&Interaction, &mut BackgroundColor), ResumeButtonFilter>,
+) {
+    for (interaction, mut color) in button_query.iter_mut() {
+        match *interaction {
+            Interaction::Clicked => state_events.send(StateEvent::Pause(false)),
+            Interaction::Hovered => *color = BackgroundColor(game_state.theme.tool.hover),
+            Interaction::None => *color = BackgroundColor(game_state.theme.tool.bg),
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use bevy::prelude::*;
+
+use crate::{
+    evt::StateEvent,
+    rsc::{
+        game_state::{Action, GameState},
+        generator::Generator,
+    },
+    ui::{spawn_completion_screen, CompletionButton, CompletionScreen},
+};
+
+pub fn completion_screen_system(
+    mut cmd: Commands,
+    game_state: Res<GameState>,
+    asset_server: Res<AssetServer>,
+    screen_query: Query<Entity, With<CompletionScreen>>,
+) {
+    let screen = screen_query.iter().next();
+    match (game_state.completed, screen) {
+        (true, None) => spawn_completion_screen(&mut cmd, &game_state, &asset_server),
+        (false, Some(screen)) => cmd.entity(screen).despawn_recursive(),
+        _ => {}
+    }
+}
+
+// A new puzzle is like the one just finished. The screen stays up until it's
+// ready, showing how far along it is.
+pub fn completion_button_system(
+    game_state: Res<GameState>,
+    generator: Res<Generator>,
+    mut state_events: EventWriter<StateEvent>,
+    mut button_query: Query<(
+        &Interaction,
+        ChangeTrackers<Interaction>,
+        &CompletionButton,
+        &mut BackgroundColor,
+        &Children,
+    )>,
+    mut text_query: Query<&mut Text>,
+) {
+    for (interaction, tracker, button, mut color, children) in button_query.iter_mut() {
+        if tracker.is_changed() && *interaction == Interaction::Clicked {
+            let action = match button {
+                CompletionButton::NewPuzzle => Action::Generate,
+                CompletionButton::PlayAgain => Action::PlayAgain,
+            };
+            state_events.send(StateEvent::Action(action));
+        }
+
+        let bg = match *interaction {
+            Interaction::Hovered | Interaction::Clicked => game_state.theme.tool.hover,
+            Interaction::None => game_state.theme.tool.bg,
+        };
+        if color.0 != bg {
+            *color = BackgroundColor(bg);
+        }
+
+        let label = match (button, generator.job()) {
+            (CompletionButton::NewPuzzle, Some(job)) => job.describe(),
+            (CompletionButton::NewPuzzle, None) => "New puzzle".to_string(),
+            (CompletionButton::PlayAgain, _) => "Play again".to_string(),
+        };
+        if let Ok(mut text) = text_query.get_mut(children[0]) {
+            if text.sections[0].value != label {
+                text.sections[0].value = label;
+            }
+        }
+    }
+}
@@ -0,0 +1,179 @@
+use bevy::prelude::*;
+
+use crate::{
+    core::focus::PinnedFocus,
+    evt::{StateEvent, ThemeChangedEvent},
+    rsc::game_state::GameState,
+    ui::{FocusList, PinButton, PinRow, UnpinButton},
+};
+
+// Lists the pins under the pin buttons, rebuilt whenever they change. Each
+// row has a swatch of the layer it's drawn with, dimmed while it's off.
+pub fn focus_panel_system(
+    mut cmd: Commands,
+    game_state: Res<GameState>,
+    asset_server: Res<AssetServer>,
+    list_query: Query<Entity, With<FocusList>>,
+    mut theme_changed_event: EventReader<ThemeChangedEvent>,
+    mut shown: Local<Option<Vec<PinnedFocus>>>,
+) {
+    if theme_changed_event.iter().count() > 0 {
+        *shown = None;
+    }
+    if shown.as_ref() == Some(&game_state.pinned) {
+        return;
+    }
+    let list = match list_query.iter().next() {
+        Some(list) => list,
+        None => return,
+    };
+    *shown = Some(game_state.pinned.clone());
+
+    let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+    cmd.entity(list).despawn_descendants();
+    cmd.entity(list).with_children(|list| {
+        for (i, pin) in game_state.pinned.iter().enumerate() {
+            let mut swatch = game_state.theme.grid.focus_layer(pin.layer);
+            let mut color = game_state.theme.tool.text;
+            swatch.set_a(if pin.active { 1.0 } else { 0.3 });
+            if !pin.active {
+                color.set_a(0.4);
+            }
+
+            list.spawn((
+                ButtonBundle {
+                    style: Style {
+                        size: Size::new(bevy::ui::Val::Percent(100.0), bevy::ui::Val::Px(28.0)),
+                        margin: UiRect::top(bevy::ui::Val::Px(2.0)),
+                        padding: UiRect::horizontal(bevy::ui::Val::Px(5.0)),
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    background_color: BackgroundColor(game_state.theme.tool.bg),
+                    ..default()
+                },
+                PinRow(i),
+            ))
+            .with_children(|row| {
+                row.spawn(NodeBundle {
+                    style: Style {
+                        size: Size::new(bevy::ui::Val::Px(14.0), bevy::ui::Val::Px(14.0)),
+                        margin: UiRect::right(bevy::ui::Val::Px(6.0)),
+                        ..default()
+                    },
+                    background_color: BackgroundColor(swatch),
+                    ..default()
+                });
+                row.spawn(TextBundle::from_section(
+                    pin.mode.label(&game_state.graph),
+                    TextStyle {
+                        font: font.clone(),
+                        font_size: 20.0,
+                        color,
+                    },
+                ));
+                row.spawn((
+                    ButtonBundle {
+                        style: Style {
+                            size: Size::new(bevy::ui::Val::Px(22.0), bevy::ui::Val::Px(22.0)),
+                            margin: UiRect::left(bevy::ui::Val::Auto),
+                            align_items: AlignItems::Center,
+                            justify_content: JustifyContent::Center,
+                            ..default()
+                        },
+                        background_color: BackgroundColor(game_state.theme.tool.panel_bg),
+                        ..default()
+                    },
+                    UnpinButton(i),
+                ))
+                .with_children(|button| {
+                    button.spawn(TextBundle::from_section(
+                        "x",
+                        TextStyle {
+                            font: font.clone(),
+                            font_size: 18.0,
+                            color: game_state.theme.tool.text,
+                        },
+                    ));
+                });
+            });
+        }
+    });
+}
+
+// Greyed out like the action buttons while there's nothing of their kind to
+// pin, or no room for another pin
+pub fn pin_button_system(
+    game_state: Res<GameState>,
+    mut state_events: EventWriter<StateEvent>,
+    mut button_query: Query<(
+        &Interaction,
+        ChangeTrackers<Interaction>,
+        &PinButton,
+        &mut BackgroundColor,
+        &Children,
+    )>,
+    mut text_query: Query<&mut Text>,
+) {
+    for (interaction, tracker, button, mut color, children) in button_query.iter_mut() {
+        let mode = game_state.focus_to_pin(button.0);
+        if tracker.is_changed() && *interaction == Interaction::Clicked {
+            if let Some(mode) = &mode {
+                state_events.send(StateEvent::Pin(mode.clone()));
+            }
+        }
+
+        let bg = match *interaction {
+            _ if mode.is_none() => game_state.theme.tool.panel_bg,
+            Interaction::Hovered | Interaction::Clicked => game_state.theme.tool.hover,
+            Interaction::None => game_state.theme.tool.bg,
+        };
+        if color.0 != bg {
+            *color = BackgroundColor(bg);
+        }
+
+        let mut text_color = game_state.theme.tool.text;
+        if mode.is_none() {
+            text_color.set_a(0.4);
+        }
+        if let Ok(mut text) = text_query.get_mut(children[0]) {
+            if text.sections[0].style.color != text_color {
+                text.sections[0].style.color = text_color;
+            }
+        }
+    }
+}
+
+// What the pin rows and their unpin buttons are queried for
+type PinRowItem = (
+    &'static Interaction,
+    &'static PinRow,
+    &'static mut BackgroundColor,
+);
+type UnpinItem = (
+    &'static Interaction,
+    &'static UnpinButton,
+    &'static mut BackgroundColor,
+);
+
+pub fn pin_row_system(
+    game_state: Res<GameState>,
+    mut state_events: EventWriter<StateEvent>,
+    mut row_query: Query<PinRowItem, (Changed<Interaction>, Without<UnpinButton>)>,
+    mut unpin_query: Query<UnpinItem, (Changed<Interaction>, Without<PinRow>)>,
+) {
+    for (interaction, row, mut color) in row_query.iter_mut() {
+        match *interaction {
+            Interaction::Clicked => state_events.send(StateEvent::TogglePin(row.0)),
+            Interaction::Hovered => *color = BackgroundColor(game_state.theme.tool.hover),
+            Interaction::None => *color = BackgroundColor(game_state.theme.tool.bg),
+        }
+    }
+    for (interaction, button, mut color) in unpin_query.iter_mut() {
+        match *interaction {
+            Interaction::Clicked => state_events.send(StateEvent::Unpin(button.0)),
+            Interaction::Hovered => *color = BackgroundColor(game_state.theme.tool.hover),
+            Interaction::None => *color = BackgroundColor(game_state.theme.tool.panel_bg),
+        }
+    }
+}
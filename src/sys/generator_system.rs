@@ -0,0 +1,93 @@
+use bevy::{prelude::*, window::RequestRedraw};
+
+use crate::{
+    evt::StateEvent,
+    rsc::{
+        game_state::GameState,
+        generator::{Generator, JobKind},
+        pool::{PuzzlePool, POOL_PATH},
+    },
+    ui::{ProgressPanel, ProgressText},
+};
+
+// Hands back whatever finished in the background to be applied like any other
+// change. Escape gives up on it, unless the new puzzle dialog is open to do that.
+pub fn generator_system(
+    keyboard: Res<Input<KeyCode>>,
+    game_state: Res<GameState>,
+    mut generator: ResMut<Generator>,
+    mut pool: ResMut<PuzzlePool>,
+    mut state_events: EventWriter<StateEvent>,
+    mut redraw: EventWriter<RequestRedraw>,
+) {
+    if let Some((settings, puzzle)) = generator.refilled() {
+        pool.add(settings, &puzzle);
+        if let Err(e) = pool.save(POOL_PATH) {
+            eprintln!("Unable to save puzzle pool: {}", e);
+        }
+    }
+    // The pool's only topped up while nothing's being waited on
+    if !generator.busy() {
+        if let Some(settings) = pool.next_refill() {
+            generator.start_refill(settings);
+        }
+    }
+
+    if let Some((kind, puzzle)) = generator.finished() {
+        match (kind, puzzle) {
+            (JobKind::Puzzle(settings), Some(puzzle)) => {
+                state_events.send(StateEvent::Generated(settings, Box::new(puzzle)))
+            }
+            (JobKind::Solve, Some(puzzle)) => {
+                state_events.send(StateEvent::Solved(Box::new(puzzle.solution)))
+            }
+            (JobKind::Puzzle(settings), None) => {
+                eprintln!("Unable to make a {} puzzle", settings.layout.name())
+            }
+            (JobKind::Solve, None) => eprintln!("Unable to find a single solution"),
+        }
+        return;
+    }
+    if !generator.busy() {
+        return;
+    }
+
+    // The window only updates on input otherwise, so the result would wait
+    // for the mouse to move
+    redraw.send(RequestRedraw);
+
+    if keyboard.just_pressed(KeyCode::Escape) && game_state.puzzle_dialog.is_none() {
+        generator.cancel();
+    }
+}
+
+pub fn progress_system(
+    game_state: Res<GameState>,
+    generator: Res<Generator>,
+    mut panel_query: Query<&mut Style, With<ProgressPanel>>,
+    mut text_query: Query<&mut Text, With<ProgressText>>,
+) {
+    // The dialog shows its own progress
+    let job = generator
+        .job()
+        .filter(|_| game_state.puzzle_dialog.is_none());
+
+    let display = match job {
+        Some(_) => Display::Flex,
+        None => Display::None,
+    };
+    for mut style in panel_query.iter_mut() {
+        if style.display != display {
+            style.display = display;
+        }
+    }
+
+    if let Some(job) = job {
+        let value = format!("{} Esc cancels", job.describe());
+        for mut text in text_query.iter_mut() {
+            if text.sections[0].value != value {
+                text.sections[0].value = value.clone();
+            }
+        }
+    }
+}
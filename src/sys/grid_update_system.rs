@@ -3,15 +3,21 @@ use std::collections::HashSet;
 use bevy::prelude::*;
 
 use crate::{
-    core::value::{from_val, to_val, Value},
-    evt::{CornerMarkEvent, FocusModeEvent},
-    rsc::game_state::{GameState, Markers},
-    ui::{GridMark, Location},
+    core::{
+        graph::{DigitFocus, Mistake},
+        value::from_val,
+    },
+    evt::CornerMarkEvent,
+    rsc::{
+        game_state::{GameState, CORNER_SLOTS},
+        view::BoardView,
+    },
+    ui::{ColorSegment, FontScale, GridMark, Location, StampLabel},
 };
 
 #[derive(Component, Clone, Debug, Default)]
 pub struct GridCell {
-    pub index: u8,
+    pub index: u16,
     pub x: u8,
     pub y: u8,
     pub corner_marks: Vec<u8>,
@@ -19,7 +25,6 @@ pub struct GridCell {
     pub value: u8,
     pub mutable: bool,
     pub selected: bool,
-    pub focused: bool,
     pub hovered: bool,
     pub invalid: bool,
 }
@@ -27,28 +32,6 @@ pub struct GridCell {
 #[derive(Component)]
 pub struct GridLabel;
 
-pub fn focus_mode_system(
-    mut game_state: ResMut<GameState>,
-    mut focus_mode_event: EventReader<FocusModeEvent>,
-    mut query: Query<&mut GridCell>,
-) {
-    for event in focus_mode_event.iter() {
-        match event {
-            FocusModeEvent(value) => {
-                for mut cell in query.iter_mut() {
-                    let gc = game_state.graph.index(cell.index).unwrap().to_owned();
-                    for p in &gc.neighbors {
-                        let neighbor = game_state.graph.at(p.x, p.y).unwrap();
-                        if neighbor.value == from_val(*value) {
-                            cell.focused = true;
-                        }
-                    }
-                }
-            }
-        }
-    }
-}
-
 pub fn corner_mark_system(
     mut corner_mark_event: EventReader<CornerMarkEvent>,
     mut query: Query<(&mut Text, &Location), With<GridLabel>>,
@@ -59,54 +42,170 @@ pub fn corner_mark_system(
     }
 }
 
+// Center marks shrink as they fill up so all nine still fit on one line
+fn center_mark_scale(count: usize) -> f32 {
+    match count {
+        0..=3 => 1.0,
+        4..=5 => 0.8,
+        6..=7 => 0.65,
+        _ => 0.55,
+    }
+}
+
 pub fn corner_mark_update_system(
-    mut query: Query<(&mut Text, &Location, &Name), With<GridLabel>>,
+    view: Res<BoardView>,
+    mut query: Query<(&mut Text, &Location, &Name, &FontScale), With<GridLabel>>,
     mut cell_query: Query<&mut GridCell>,
 ) {
-    for (mut text, loc, name) in &mut query.iter_mut() {
+    for (mut text, loc, name, scale) in &mut query.iter_mut() {
         let gc = cell_query.iter_mut().find(|c| c.index == loc.0).unwrap();
-        if gc.value == 0 && gc.corner_marks.len() > 0 {
+        if gc.value == 0 {
             let mut sorted = gc.corner_marks.clone();
             sorted.sort();
             let corner_marks: Vec<String> = sorted.iter_mut().map(|n| n.to_string()).collect();
-            text.sections[0].value = match name.as_str() {
-                "TL" => corner_marks.get(0),
-                "TR" => corner_marks.get(1),
-                "BL" => corner_marks.get(2),
-                "BR" => corner_marks.get(3),
-                // "Center" => Some(
-                //     gc.center_marks
-                //         .iter()
-                //         .map(|n| n.to_string())
-                //         .collect::<Vec<String>>()
-                //         .join(""),
-                // ),
+            let slot = CORNER_SLOTS.iter().position(|s| *s == name.as_str());
+            text.sections[0].value = match (slot, name.as_str()) {
+                (Some(slot), _) => corner_marks.get(slot).cloned(),
+                (None, "Center") => {
+                    text.sections[0].style.font_size =
+                        view.font_size(scale.0) * center_mark_scale(gc.center_marks.len());
+                    Some(
+                        gc.center_marks
+                            .iter()
+                            .map(|n| n.to_string())
+                            .collect::<Vec<String>>()
+                            .join(""),
+                    )
+                }
                 _ => None,
             }
-            .unwrap_or(&"".to_string())
-            .to_string();
+            .unwrap_or_default();
+        }
+    }
+}
+
+pub fn cell_color_system(
+    game_state: Res<GameState>,
+    mut query: Query<(&ColorSegment, &Location, &mut Style, &mut BackgroundColor)>,
+) {
+    let palette = game_state.palette();
+    for (segment, loc, mut style, mut color) in query.iter_mut() {
+        match game_state
+            .graph
+            .cell(loc.0)
+            .and_then(|c| c.colors.get(segment.0))
+        {
+            Some(&n) => {
+                style.display = Display::Flex;
+                // Keep some of the cell's state color visible through the paint
+                let mut paint = palette.color(n);
+                paint.set_a(0.75);
+                *color = paint.into();
+            }
+            None => {
+                style.display = Display::None;
+            }
         }
     }
 }
 
+// Stamps share the cell, so they shrink as more are added
+fn stamp_scale(count: usize) -> f32 {
+    match count {
+        0..=1 => 1.0,
+        2 => 0.7,
+        _ => 0.5,
+    }
+}
+
+pub fn stamp_update_system(
+    game_state: Res<GameState>,
+    view: Res<BoardView>,
+    mut query: Query<(&Location, &FontScale, &mut Text), With<StampLabel>>,
+) {
+    let palette = game_state.palette();
+    for (loc, scale, mut text) in query.iter_mut() {
+        let stamps = match game_state.graph.cell(loc.0) {
+            Some(cell) => &cell.stamps,
+            None => continue,
+        };
+
+        let font_size = view.font_size(scale.0) * stamp_scale(stamps.len());
+        let sections: Vec<(&str, Color)> = stamps
+            .iter()
+            .map(|s| (s.shape.glyph(), palette.color(s.color)))
+            .collect();
+        let current: Vec<(&str, Color)> = text
+            .sections
+            .iter()
+            .filter(|s| !s.value.is_empty())
+            .map(|s| (s.value.as_str(), s.style.color))
+            .collect();
+        if sections == current && text.sections[0].style.font_size == font_size {
+            continue;
+        }
+
+        // Keep one section around so the font handle isn't lost when empty
+        let style = text.sections[0].style.clone();
+        text.sections = sections
+            .iter()
+            .map(|&(glyph, color)| TextSection {
+                value: glyph.to_string(),
+                style: TextStyle {
+                    color,
+                    font_size,
+                    ..style.clone()
+                },
+            })
+            .collect();
+        if text.sections.is_empty() {
+            text.sections.push(TextSection {
+                value: String::new(),
+                style,
+            });
+        }
+    }
+}
+
+// Pins blend over each other so cells covered by more than one show it
+fn overlay(under: Color, over: Color) -> Color {
+    let a = over.a();
+    Color::rgb(
+        under.r() + (over.r() - under.r()) * a,
+        under.g() + (over.g() - under.g()) * a,
+        under.b() + (over.b() - under.b()) * a,
+    )
+}
+
 pub fn grid_update_system(
-    mut game_state: ResMut<GameState>,
+    game_state: Res<GameState>,
     mut query: Query<(&mut GridCell, &Children)>,
     mut b_query: Query<(&mut Button, &mut BackgroundColor, &Children)>,
     mut t_query: Query<&mut Text, With<GridLabel>>,
     mut m_query: Query<(&mut GridMark, &mut Style, &Children)>,
 ) {
     let invalid_cells = game_state.graph.invalid_cells();
+    let mistakes: Vec<Mistake> = game_state
+        .mistakes
+        .iter()
+        .filter(|m| m.still_in(&game_state.graph))
+        .cloned()
+        .collect();
 
     for (mut cell, children) in &mut query.iter_mut() {
-        game_state.graph.index(cell.index).map(|gc| {
+        game_state.graph.cell(cell.index).map(|gc| {
             cell.value = gc.value;
+            cell.corner_marks = gc.corner_marks.clone();
+            cell.center_marks = gc.center_marks.clone();
             if gc.value == 0 {
                 cell.mutable = true;
             }
         });
 
         let invalid = invalid_cells.contains(&cell.index);
+        let focus = game_state
+            .graph
+            .digit_focus(cell.index, from_val(game_state.focus_value));
 
         for &child in children.iter() {
             let button = b_query.get_mut(child);
@@ -114,14 +213,29 @@ pub fn grid_update_system(
                 Ok((_, mut color, btn_children)) => {
                     *color = game_state.theme.grid.bg.into();
 
-                    if cell.focused {
-                        *color = game_state.theme.grid.focused.into();
+                    let grid = &game_state.theme.grid;
+                    for pin in game_state.pinned.iter().filter(|p| p.active) {
+                        if pin.mode.covers(&game_state.graph, cell.index) {
+                            color.0 = overlay(color.0, grid.focus_layer(pin.layer));
+                        }
+                    }
+                    match focus {
+                        Some(DigitFocus::Candidate) => *color = grid.focus_candidate.into(),
+                        Some(DigitFocus::Excluded) => *color = grid.focus_excluded.into(),
+                        Some(DigitFocus::Marked) => *color = grid.focus_marked.into(),
+                        Some(DigitFocus::Placed) | None => {}
                     }
 
                     if invalid {
                         *color = game_state.theme.grid.invalid.into();
                     }
 
+                    match mistakes.iter().find(|m| m.index() == cell.index) {
+                        Some(Mistake::Digit(..)) => *color = grid.mistake.into(),
+                        Some(Mistake::Elimination(..)) => *color = grid.mistake_marks.into(),
+                        None => {}
+                    }
+
                     if game_state.cursor_pos == cell.index {
                         *color = game_state.theme.grid.cursor.into();
                     }
@@ -134,7 +248,7 @@ pub fn grid_update_system(
                         *color = game_state.theme.grid.selected.into();
                     }
 
-                    if game_state.focus_value == to_val(cell.value) && cell.value != 0 {
+                    if focus == Some(DigitFocus::Placed) {
                         *color = game_state.theme.grid.focused.into();
                     }
 
@@ -154,58 +268,12 @@ pub fn grid_update_system(
                             Err(_) => {}
                         }
 
-                        for (mut grid_marker, mut style, children) in &mut m_query.iter_mut() {
+                        if let Ok((_, mut style, _)) = m_query.get_mut(btn_child) {
                             if cell.value == 0 {
                                 style.display = Display::Flex;
                             } else {
                                 style.display = Display::None;
                             }
-
-                            // for &child in children.iter() {
-                            //     match t_query.get_mut(child) {
-                            //         Ok(mut txt) => {
-                            //             if cell.value == 0 {
-                            //                 match grid_marker.0 {
-                            //                     Markers::TL => {
-                            //                         if cell.corner_marks.len() > 0 {
-                            //                             txt.sections[0].value =
-                            //                                 cell.corner_marks[0].to_string();
-                            //                         }
-                            //                     }
-                            //                     Markers::TR => {
-                            //                         if cell.corner_marks.len() > 1 {
-                            //                             txt.sections[0].value =
-                            //                                 cell.corner_marks[1].to_string();
-                            //                         }
-                            //                     }
-                            //                     Markers::BL => {
-                            //                         if cell.corner_marks.len() > 2 {
-                            //                             txt.sections[0].value =
-                            //                                 cell.corner_marks[2].to_string();
-                            //                         }
-                            //                     }
-                            //                     Markers::BR => {
-                            //                         if cell.corner_marks.len() > 3 {
-                            //                             txt.sections[0].value =
-                            //                                 cell.corner_marks[3].to_string();
-                            //                         }
-                            //                     }
-                            //                     Markers::Center => {
-                            //                         if cell.center_marks.len() > 0 {
-                            //                             txt.sections[0].value = cell
-                            //                                 .center_marks
-                            //                                 .iter()
-                            //                                 .map(|x| x.to_string())
-                            //                                 .collect::<Vec<String>>()
-                            //                                 .join("");
-                            //                         }
-                            //                     }
-                            //                 }
-                            //             }
-                            //     }
-                            //     Err(_) => {}
-                            // }
-                            // }
                         }
                     }
                 }
@@ -0,0 +1,168 @@
+use bevy::prelude::*;
+
+use crate::{
+    evt::{StateEvent, ThemeChangedEvent},
+    rsc::game_state::{Action, GameState},
+    ui::{ActionButton, HistoryPanel, HistoryRow},
+};
+
+// Rows that fit in the panel under its title
+const VISIBLE_ROWS: usize = 10;
+
+// Lists the current branch around the current step, rebuilt whenever it
+// moves. Steps that can be redone are dimmed, and steps where history split
+// show which of the branches they're on.
+pub fn history_panel_system(
+    mut cmd: Commands,
+    game_state: Res<GameState>,
+    asset_server: Res<AssetServer>,
+    panel_query: Query<Entity, With<HistoryPanel>>,
+    mut theme_changed_event: EventReader<ThemeChangedEvent>,
+    mut shown: Local<Option<(usize, usize)>>,
+) {
+    // Rows are colored when they're built, so a new theme rebuilds them
+    if theme_changed_event.iter().count() > 0 {
+        *shown = None;
+    }
+    let history = &game_state.history;
+    let state = (history.len(), history.current());
+    if *shown == Some(state) {
+        return;
+    }
+    let panel = match panel_query.iter().next() {
+        Some(panel) => panel,
+        None => return,
+    };
+    *shown = Some(state);
+
+    let mut steps = vec![0];
+    steps.extend(history.branch());
+    let current = steps
+        .iter()
+        .position(|&n| n == history.current())
+        .unwrap_or(0);
+    let first = current
+        .saturating_sub(VISIBLE_ROWS / 2)
+        .min(steps.len().saturating_sub(VISIBLE_ROWS));
+
+    let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+    cmd.entity(panel).despawn_descendants();
+    cmd.entity(panel).with_children(|panel| {
+        panel.spawn(TextBundle::from_section(
+            "History",
+            TextStyle {
+                font: font.clone(),
+                font_size: 30.0,
+                color: game_state.theme.tool.text,
+            },
+        ));
+        for (i, &node) in steps.iter().enumerate().skip(first).take(VISIBLE_ROWS) {
+            let mut label = match node {
+                0 => "Start".to_string(),
+                _ => history.label(node).to_string(),
+            };
+            let (branch, branches) = history.branches(node);
+            if branches > 1 {
+                label.push_str(&format!(" ({}/{})", branch + 1, branches));
+            }
+            let mut color = game_state.theme.tool.text;
+            if i > current {
+                color.set_a(0.4);
+            }
+            let bg = if i == current {
+                game_state.theme.tool.selected
+            } else {
+                game_state.theme.tool.bg
+            };
+
+            panel
+                .spawn((
+                    ButtonBundle {
+                        style: Style {
+                            size: Size::new(bevy::ui::Val::Percent(100.0), bevy::ui::Val::Px(28.0)),
+                            padding: UiRect::horizontal(bevy::ui::Val::Px(5.0)),
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        background_color: BackgroundColor(bg),
+                        ..default()
+                    },
+                    HistoryRow(node),
+                ))
+                .with_children(|row| {
+                    row.spawn(TextBundle::from_section(
+                        label,
+                        TextStyle {
+                            font: font.clone(),
+                            font_size: 20.0,
+                            color,
+                        },
+                    ));
+                });
+        }
+    });
+}
+
+// Clicking a step undoes or redoes to it, across branches if need be
+pub fn history_row_system(
+    game_state: Res<GameState>,
+    mut state_events: EventWriter<StateEvent>,
+    mut row_query: Query<(&Interaction, &HistoryRow, &mut BackgroundColor), Changed<Interaction>>,
+) {
+    for (interaction, row, mut color) in row_query.iter_mut() {
+        let current = row.0 == game_state.history.current();
+        match *interaction {
+            Interaction::Clicked => {
+                state_events.send(StateEvent::Action(Action::GotoHistory(row.0)));
+            }
+            Interaction::Hovered if !current => {
+                *color = BackgroundColor(game_state.theme.tool.hover);
+            }
+            _ if !current => {
+                *color = BackgroundColor(game_state.theme.tool.bg);
+            }
+            _ => {}
+        }
+    }
+}
+
+// Undo, redo, restart and clear marks. They're checked every frame since
+// almost any change can enable or disable them.
+pub fn action_button_system(
+    game_state: Res<GameState>,
+    mut state_events: EventWriter<StateEvent>,
+    mut button_query: Query<(
+        &Interaction,
+        ChangeTrackers<Interaction>,
+        &ActionButton,
+        &mut BackgroundColor,
+        &Children,
+    )>,
+    mut text_query: Query<&mut Text>,
+) {
+    for (interaction, tracker, button, mut color, children) in button_query.iter_mut() {
+        let enabled = game_state.enabled(&button.0);
+        if enabled && tracker.is_changed() && *interaction == Interaction::Clicked {
+            state_events.send(StateEvent::Action(button.0.clone()));
+        }
+
+        let bg = match *interaction {
+            _ if !enabled => game_state.theme.tool.panel_bg,
+            Interaction::Hovered | Interaction::Clicked => game_state.theme.tool.hover,
+            Interaction::None => game_state.theme.tool.bg,
+        };
+        if color.0 != bg {
+            *color = BackgroundColor(bg);
+        }
+
+        let mut text_color = game_state.theme.tool.text;
+        if !enabled {
+            text_color.set_a(0.4);
+        }
+        if let Ok(mut text) = text_query.get_mut(children[0]) {
+            if text.sections[0].style.color != text_color {
+                text.sections[0].style.color = text_color;
+            }
+        }
+    }
+}
@@ -1,45 +1,53 @@
-use bevy::prelude::*;
+use bevy::{ecs::system::SystemParam, prelude::*};
 
 use crate::{
-    core::value::Value,
-    rsc::game_state::{Action, GameState, Modifier, MouseState, Tools},
+    core::{
+        graph::Graph,
+        value::{to_val, Value},
+    },
+    evt::{AccessibilityEvent, NextThemeEvent, Selection, StateEvent, ToolSelectedEvent},
+    rsc::{
+        game_state::{Action, GameState, PuzzleDialog, Tools},
+        input_state::{InputState, Modifier, MouseState},
+        keymap::{Binding, KeyChord, Keymap},
+    },
 };
 
-pub fn mouse_system(buttons: Res<Input<MouseButton>>, mut game_state: ResMut<GameState>) {
+pub fn mouse_system(buttons: Res<Input<MouseButton>>, mut input: ResMut<InputState>) {
     if buttons.just_pressed(MouseButton::Left) {
-        game_state.mouse = MouseState::Pressed;
-        game_state.cursor_pos = 0;
+        input.mouse = MouseState::Pressed;
     }
 
     if buttons.just_released(MouseButton::Left) {
-        game_state.mouse = MouseState::Released;
+        input.mouse = MouseState::Released;
     }
 }
 
-pub fn keyboard_system(keyboard: Res<Input<KeyCode>>, mut game_state: ResMut<GameState>) {
-    if keyboard.just_pressed(KeyCode::Escape) {
-        game_state.mouse = MouseState::None;
-        game_state.cursor_pos = 0;
-        game_state.action = Action::ClearSelection;
-    }
-
-    if keyboard.just_released(KeyCode::Tab) {
-        game_state.action = Action::Generate;
-    }
-
-    if keyboard.just_pressed(KeyCode::Return) {
-        game_state.action = Action::Solve;
-    }
+// Everything a key press can send
+#[derive(SystemParam)]
+pub struct KeyboardEvents<'w, 's> {
+    tool_selected: EventWriter<'w, 's, ToolSelectedEvent>,
+    state: EventWriter<'w, 's, StateEvent>,
+    next_theme: EventWriter<'w, 's, NextThemeEvent>,
+    accessibility: EventWriter<'w, 's, AccessibilityEvent>,
+}
 
-    if keyboard.just_released(KeyCode::Space) {
-        game_state.tool = match game_state.tool {
-            Tools::Select => Tools::CornerMark,
-            Tools::CornerMark => Tools::CenterMark,
-            Tools::CenterMark => Tools::Fill,
-            Tools::Fill => Tools::Erase,
-            Tools::Erase => Tools::Select,
-            Tools::None => Tools::Select,
-        };
+pub fn keyboard_system(
+    keyboard: Res<Input<KeyCode>>,
+    keymap: Res<Keymap>,
+    game_state: Res<GameState>,
+    mut input: ResMut<InputState>,
+    mut events: KeyboardEvents,
+    mut held_tool: Local<Option<Tools>>,
+) {
+    // Typing a note or a new binding shouldn't trigger shortcuts, those
+    // systems own the keyboard while they're open
+    if game_state.editing_note.is_some()
+        || game_state.settings_open
+        || game_state.stats_open
+        || game_state.puzzle_dialog.is_some()
+    {
+        return;
     }
 
     if keyboard.any_just_released([
@@ -50,89 +58,254 @@ pub fn keyboard_system(keyboard: Res<Input<KeyCode>>, mut game_state: ResMut<Gam
         KeyCode::LAlt,
         KeyCode::RAlt,
     ]) {
-        game_state.modifier = Modifier::None;
+        input.modifier = Modifier::None;
     }
 
     if keyboard.any_pressed([KeyCode::LShift, KeyCode::RShift]) {
-        game_state.modifier = Modifier::Shift;
+        input.modifier = Modifier::Shift;
     }
 
     if keyboard.any_pressed([KeyCode::LControl, KeyCode::RControl]) {
-        game_state.modifier = Modifier::Ctrl;
+        input.modifier = Modifier::Ctrl;
     }
 
     if keyboard.any_pressed([KeyCode::LAlt, KeyCode::RAlt]) {
-        game_state.modifier = Modifier::Alt;
+        input.modifier = Modifier::Alt;
     }
 
-    if keyboard.just_pressed(KeyCode::Left) {
-        if game_state.cursor_pos > 0 {
-            if (game_state.cursor_pos - 1) % 9 != 0 {
-                game_state.cursor_pos = game_state.cursor_pos - 1;
-            }
-        }
-        game_state.action = Action::Undo
+    // Light up the tool a held modifier switches to, and the selected one
+    // again once it's let go
+    let held = temporary_tool(
+        keyboard.any_pressed([KeyCode::LShift, KeyCode::RShift]),
+        keyboard.any_pressed([KeyCode::LControl, KeyCode::RControl]),
+        keyboard.any_pressed([KeyCode::LAlt, KeyCode::RAlt]),
+    );
+    if held != *held_tool {
+        events
+            .tool_selected
+            .send(ToolSelectedEvent(held.unwrap_or(game_state.tool)));
+        *held_tool = held;
     }
 
-    if keyboard.just_pressed(KeyCode::Right) {
-        if game_state.cursor_pos < 80 {
-            if (game_state.cursor_pos) % 9 != 0 {
-                game_state.cursor_pos = game_state.cursor_pos + 1;
+    for &key in keyboard.get_just_pressed() {
+        let chord = KeyChord::pressed(key, &keyboard);
+        if let Some(binding) = keymap.binding_for(&chord) {
+            println!(
+                "Key pressed {}; binding: {}",
+                chord.label(),
+                binding.label()
+            );
+            match binding {
+                Binding::Settings => events.state.send(StateEvent::Settings(true)),
+                Binding::Stats => events.state.send(StateEvent::Stats(true)),
+                Binding::NextTheme => events.next_theme.send(NextThemeEvent),
+                Binding::ToggleCues => events.accessibility.send(AccessibilityEvent::ToggleCues),
+                Binding::TextLarger => events.accessibility.send(AccessibilityEvent::TextSize(1)),
+                Binding::TextSmaller => events.accessibility.send(AccessibilityEvent::TextSize(-1)),
+                Binding::NewPuzzle => {
+                    events
+                        .state
+                        .send(StateEvent::PuzzleDialog(Some(PuzzleDialog::new(
+                            game_state.puzzle,
+                        ))))
+                }
+                // A game in progress is only thrown away once the dialog
+                // confirms it
+                Binding::NextLayout if game_state.graph.has_entries() => {
+                    let mut dialog = PuzzleDialog::new(game_state.puzzle);
+                    dialog.settings.layout = game_state.layout.next();
+                    events.state.send(StateEvent::PuzzleDialog(Some(dialog)));
+                }
+                // Also stops a drag from selecting any more cells
+                Binding::Action(Action::ClearSelection) => {
+                    input.mouse = MouseState::None;
+                    apply_binding(binding, &chord, &game_state, &mut events.state);
+                }
+                _ => apply_binding(binding, &chord, &game_state, &mut events.state),
             }
         }
-        game_state.action = Action::Redo
     }
+}
 
-    if keyboard.just_pressed(KeyCode::Up) {
-        if game_state.cursor_pos > 8 {
-            game_state.cursor_pos = game_state.cursor_pos - 9;
-        }
+// Tool digits go to while modifiers are held, whatever tool is selected
+fn temporary_tool(shift: bool, ctrl: bool, alt: bool) -> Option<Tools> {
+    match (shift, ctrl, alt) {
+        (_, _, true) | (true, true, _) => Some(Tools::Color),
+        (false, true, _) => Some(Tools::CenterMark),
+        (true, false, _) => Some(Tools::CornerMark),
+        _ => None,
     }
+}
 
-    if keyboard.just_pressed(KeyCode::Down) {
-        if game_state.cursor_pos < 72 {
-            game_state.cursor_pos = game_state.cursor_pos + 9;
+// Next cell from `from` in the direction (dx, dy), `step` cells at a time.
+// Holes in the layout are skipped over and the cursor stays put at the edge
+// of the board.
+fn step_cursor(graph: &Graph, from: u16, dx: i16, dy: i16, step: i16) -> u16 {
+    let cell = match graph.cell(from) {
+        Some(cell) => cell,
+        None => return from,
+    };
+    let (mut x, mut y) = (cell.x as i16, cell.y as i16);
+    loop {
+        x += dx * step;
+        y += dy * step;
+        if x < 1 || y < 1 || x > graph.width() as i16 || y > graph.height() as i16 {
+            return from;
+        }
+        if graph.at(x as u8, y as u8).is_some() {
+            return graph.point_to_index(x as u8, y as u8);
         }
     }
+}
 
-    // TODO: Abstract keyboard shortcuts to a resource so they can be customized per user
-    if keyboard.just_pressed(KeyCode::F) {
-        game_state.tool = Tools::Fill; // FIXME: Updating state directly like this will cause the UI to get out of sync. Need to move all state updates to a single location, or behind event emission.
-    }
-
-    let keycodes = [
-        ([KeyCode::Key1, KeyCode::Numpad1], Value::One),
-        ([KeyCode::Key2, KeyCode::Numpad2], Value::Two),
-        ([KeyCode::Key3, KeyCode::Numpad3], Value::Three),
-        ([KeyCode::Key4, KeyCode::Numpad4], Value::Four),
-        ([KeyCode::Key5, KeyCode::Numpad5], Value::Five),
-        ([KeyCode::Key6, KeyCode::Numpad6], Value::Six),
-        ([KeyCode::Key7, KeyCode::Numpad7], Value::Seven),
-        ([KeyCode::Key8, KeyCode::Numpad8], Value::Eight),
-        ([KeyCode::Key9, KeyCode::Numpad9], Value::Nine),
-    ];
-
-    for keycode_tuple in keycodes.iter() {
-        if keyboard.any_just_pressed(keycode_tuple.0) {
-            println!(
-                "Key pressed {:?}; action: {}",
-                keycode_tuple.0, keycode_tuple.1
-            );
-            match game_state.tool {
-                Tools::CornerMark => {
-                    game_state.action = Action::CornerMark(Value::from(keycode_tuple.1));
-                }
-                Tools::CenterMark => {
-                    game_state.action = Action::CenterMark(Value::from(keycode_tuple.1));
-                }
-                Tools::Fill => {
-                    game_state.action = Action::Fill(Value::from(keycode_tuple.1));
-                }
-                Tools::Erase => {
-                    game_state.action = Action::Erase(Value::from(keycode_tuple.1));
+// Ctrl jumps a whole box, Shift adds the cells passed over to the selection
+// instead of replacing it
+fn move_cursor(
+    game_state: &GameState,
+    chord: &KeyChord,
+    dx: i16,
+    dy: i16,
+    state_events: &mut EventWriter<StateEvent>,
+) {
+    let graph = &game_state.graph;
+    let (to, passed) = match game_state.cursor_pos {
+        // Without a cursor the first press puts it on the first cell
+        0 => {
+            let first = &graph.cells[0];
+            let to = graph.point_to_index(first.x, first.y);
+            (to, vec![to])
+        }
+        from => {
+            let step = if chord.ctrl { 3 } else { 1 };
+            let to = step_cursor(graph, from, dx, dy, step);
+            // Walk there a cell at a time to pick up the ones jumped over
+            let mut passed = vec![from];
+            let mut at = from;
+            while at != to {
+                let next = step_cursor(graph, at, dx, dy, 1);
+                if next == at {
+                    break;
                 }
-                _ => {}
+                passed.push(next);
+                at = next;
             }
+            (to, passed)
+        }
+    };
+
+    state_events.send(StateEvent::Cursor(to));
+    state_events.send(StateEvent::Select(if chord.shift {
+        Selection::Add(passed)
+    } else {
+        Selection::Set(vec![to])
+    }));
+}
+
+fn apply_binding(
+    binding: &Binding,
+    chord: &KeyChord,
+    game_state: &GameState,
+    state_events: &mut EventWriter<StateEvent>,
+) {
+    match binding {
+        Binding::Action(action) => {
+            state_events.send(StateEvent::Action(action.clone()));
+        }
+        Binding::Tool(tool) => {
+            state_events.send(StateEvent::Tool(*tool));
+        }
+        Binding::NextTool => {
+            let tool = match game_state.tool {
+                Tools::Select => Tools::CornerMark,
+                Tools::CornerMark => Tools::CenterMark,
+                Tools::CenterMark => Tools::Fill,
+                Tools::Fill => Tools::Erase,
+                Tools::Erase => Tools::Color,
+                Tools::Color => Tools::Pen,
+                Tools::Pen => Tools::Stamp,
+                Tools::Stamp => Tools::Note,
+                Tools::Note => Tools::Select,
+                Tools::None => Tools::Select,
+            };
+            state_events.send(StateEvent::Tool(tool));
+        }
+        Binding::Digit(n) => {
+            let value = to_val(*n);
+            let tool =
+                temporary_tool(chord.shift, chord.ctrl, chord.alt).unwrap_or(game_state.tool);
+            let action = match tool {
+                // Shapes run past nine, so stamps also use 0
+                Tools::Stamp => Action::Stamp(*n),
+                _ if value == Value::Unknown => return,
+                Tools::CornerMark => Action::CornerMark(value),
+                Tools::CenterMark => Action::CenterMark(value),
+                Tools::Fill => Action::Fill(value),
+                Tools::Erase => Action::Erase(value),
+                Tools::Color => Action::Color(value),
+                _ => return,
+            };
+            state_events.send(StateEvent::Action(action));
+        }
+        Binding::NextLayout => {
+            state_events.send(StateEvent::Layout(game_state.layout.next()));
+            state_events.send(StateEvent::Action(Action::Generate));
+        }
+        Binding::ToggleAutoEliminate => {
+            state_events.send(StateEvent::AutoEliminate(!game_state.auto_eliminate));
+        }
+        Binding::TogglePause => {
+            state_events.send(StateEvent::Pause(!game_state.paused));
+        }
+        Binding::ToggleCheckMarks => {
+            state_events.send(StateEvent::CheckMarks(!game_state.check_marks));
+        }
+        Binding::NextPalette => {
+            let palette = (game_state.palette + 1) % game_state.theme.palettes.len();
+            state_events.send(StateEvent::Palette(palette));
+        }
+        Binding::PreviousColor => {
+            let colors = game_state.palette().colors.len() as u8;
+            let color = if game_state.color > 1 {
+                game_state.color - 1
+            } else {
+                colors
+            };
+            state_events.send(StateEvent::Color(color));
+        }
+        Binding::NextColor => {
+            let colors = game_state.palette().colors.len() as u8;
+            state_events.send(StateEvent::Color(game_state.color % colors + 1));
+        }
+        Binding::CursorLeft => move_cursor(game_state, chord, -1, 0, state_events),
+        Binding::CursorRight => move_cursor(game_state, chord, 1, 0, state_events),
+        Binding::CursorUp => move_cursor(game_state, chord, 0, -1, state_events),
+        Binding::CursorDown => move_cursor(game_state, chord, 0, 1, state_events),
+        Binding::FocusDigit => {
+            let cell = match game_state.cursor_pos {
+                0 => None,
+                index => game_state.graph.cell(index),
+            };
+            let focus = match cell {
+                Some(cell) if cell.value != 0 => game_state.toggle_focus(cell.value),
+                _ => Value::Unknown,
+            };
+            state_events.send(StateEvent::Focus(focus));
+        }
+        Binding::SelectAll => {
+            let all: Vec<u16> = game_state
+                .graph
+                .cells
+                .iter()
+                .map(|c| game_state.graph.point_to_index(c.x, c.y))
+                .collect();
+            state_events.send(StateEvent::Select(Selection::Add(all)));
         }
+        Binding::Settings
+        | Binding::Stats
+        | Binding::NextTheme
+        | Binding::ToggleCues
+        | Binding::TextLarger
+        | Binding::TextSmaller
+        | Binding::NewPuzzle => {}
     }
 }
@@ -1,6 +1,19 @@
+pub mod accessibility_system;
 pub mod actions;
+pub mod annotation_system;
+pub mod board_view_system;
 pub mod button_system;
+pub mod clock_system;
+pub mod completion_system;
+pub mod focus_system;
+pub mod generator_system;
 pub mod grid_fill_system;
 pub mod grid_update_system;
+pub mod history_system;
 pub mod input;
+pub mod note_system;
+pub mod puzzle_dialog_system;
+pub mod settings_system;
+pub mod stats_system;
 pub mod text;
+pub mod theme_system;
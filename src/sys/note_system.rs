@@ -0,0 +1,171 @@
+use bevy::prelude::*;
+
+use crate::{
+    evt::{StateEvent, ToolSelectedEvent},
+    rsc::{
+        game_state::{GameState, NoteTarget},
+        view::BoardView,
+    },
+    ui::{Location, NoteIndicator, NoteTooltip, NoteTooltipText, NotesPanel, NotesText},
+};
+
+use super::grid_update_system::GridCell;
+
+// Shown at the end of a note while it's being typed into
+const CARET: &str = "|";
+
+type NotesPanelFilter = (Changed<Interaction>, With<NotesPanel>);
+
+pub fn notes_panel_system(
+    game_state: Res<GameState>,
+    mut state_events: EventWriter<StateEvent>,
+    mut interaction_query: Query<(&Interaction, &mut BackgroundColor), NotesPanelFilter>,
+) {
+    for (interaction, mut color) in &mut interaction_query {
+        match *interaction {
+            Interaction::Clicked => {
+                state_events.send(StateEvent::EditNote(Some(NoteTarget::Puzzle)));
+            }
+            Interaction::Hovered => {
+                *color = BackgroundColor(game_state.theme.tool.hover);
+            }
+            Interaction::None => {
+                *color = BackgroundColor(game_state.theme.tool.bg);
+            }
+        }
+    }
+}
+
+// Takes typed text while a note is open. Enter starts a new line, Escape,
+// picking another tool or opening another screen closes the note.
+pub fn note_input_system(
+    keyboard: Res<Input<KeyCode>>,
+    mut chars: EventReader<ReceivedCharacter>,
+    mut tool_selected_event: EventReader<ToolSelectedEvent>,
+    game_state: Res<GameState>,
+    mut state_events: EventWriter<StateEvent>,
+) {
+    // Read every frame so keys typed before a note was opened don't end up in it
+    let typed: Vec<char> = chars
+        .iter()
+        .map(|e| e.char)
+        .filter(|c| !c.is_control())
+        .collect();
+
+    let target = match game_state.editing_note {
+        Some(target) => target,
+        None => return,
+    };
+
+    if tool_selected_event.iter().count() > 0
+        || keyboard.just_pressed(KeyCode::Escape)
+        || game_state.settings_open
+        || game_state.puzzle_dialog.is_some()
+    {
+        state_events.send(StateEvent::EditNote(None));
+        return;
+    }
+
+    let mut note = match game_state.note(target) {
+        Some(note) => note.to_string(),
+        None => return,
+    };
+    let before = note.clone();
+    if keyboard.just_pressed(KeyCode::Back) {
+        note.pop();
+    }
+    note.extend(typed);
+    if keyboard.just_pressed(KeyCode::Return) {
+        note.push('\n');
+    }
+    if note != before {
+        state_events.send(StateEvent::Note(target, note));
+    }
+}
+
+pub fn note_indicator_system(
+    game_state: Res<GameState>,
+    mut query: Query<(&Location, &mut Style), With<NoteIndicator>>,
+) {
+    for (loc, mut style) in query.iter_mut() {
+        let has_note = game_state
+            .graph
+            .cell(loc.0)
+            .is_some_and(|c| !c.note.is_empty());
+        let display = if has_note {
+            Display::Flex
+        } else {
+            Display::None
+        };
+        if style.display != display {
+            style.display = display;
+        }
+    }
+}
+
+pub fn notes_text_system(game_state: Res<GameState>, mut query: Query<&mut Text, With<NotesText>>) {
+    let editing = game_state.editing_note == Some(NoteTarget::Puzzle);
+    let notes = &game_state.graph.annotations.notes;
+    let value = match (editing, notes.is_empty()) {
+        (true, _) => format!("{}{}", notes, CARET),
+        (false, true) => "Click to add notes".to_string(),
+        (false, false) => notes.clone(),
+    };
+    for mut text in query.iter_mut() {
+        if text.sections[0].value != value {
+            text.sections[0].value = value.clone();
+        }
+    }
+}
+
+pub fn note_tooltip_system(
+    game_state: Res<GameState>,
+    view: Res<BoardView>,
+    cell_query: Query<&GridCell>,
+    mut tooltip_query: Query<&mut Style, With<NoteTooltip>>,
+    mut text_query: Query<&mut Text, With<NoteTooltipText>>,
+) {
+    // The note being edited wins over the one under the mouse
+    let shown = match game_state.editing_note {
+        Some(NoteTarget::Cell(index)) => game_state
+            .graph
+            .cell(index)
+            .map(|c| (c, format!("{}{}", c.note, CARET))),
+        _ => cell_query
+            .iter()
+            .filter(|c| c.hovered)
+            .filter_map(|c| game_state.graph.cell(c.index))
+            .find(|c| !c.note.is_empty())
+            .map(|c| (c, c.note.clone())),
+    };
+
+    for mut style in tooltip_query.iter_mut() {
+        match shown {
+            Some((cell, _)) => {
+                let position = view.cell_position(cell.x, cell.y);
+                let position = UiRect {
+                    left: Val::Px(position.x + view.cell_size() + 5.0),
+                    top: Val::Px(position.y),
+                    ..default()
+                };
+                if style.display != Display::Flex || style.position != position {
+                    style.display = Display::Flex;
+                    style.position = position;
+                }
+            }
+            None => {
+                if style.display != Display::None {
+                    style.display = Display::None;
+                }
+            }
+        }
+    }
+
+    if let Some((_, note)) = &shown {
+        for mut text in text_query.iter_mut() {
+            if &text.sections[0].value != note {
+                text.sections[0].value = note.clone();
+            }
+        }
+    }
+}
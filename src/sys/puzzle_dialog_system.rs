@@ -0,0 +1,206 @@
+use bevy::prelude::*;
+
+use crate::{
+    evt::StateEvent,
+    rsc::{
+        game_state::{Action, GameState, PuzzleDialog},
+        generator::Generator,
+    },
+    ui::{spawn_puzzle_dialog, PuzzleButton, PuzzleDialogScreen, PuzzleDialogStatus},
+};
+
+// Generating over a game in progress has to be asked for twice. The dialog
+// stays open showing progress until the puzzle's ready.
+fn generate(
+    dialog: &PuzzleDialog,
+    game_state: &GameState,
+    state_events: &mut EventWriter<StateEvent>,
+) {
+    if game_state.graph.has_entries() && !dialog.confirming {
+        let dialog = PuzzleDialog {
+            confirming: true,
+            ..dialog.clone()
+        };
+        state_events.send(StateEvent::PuzzleDialog(Some(dialog)));
+    } else {
+        state_events.send(StateEvent::Action(Action::NewPuzzle(dialog.settings)));
+    }
+}
+
+// Cancel stops a puzzle being made before it closes the dialog
+fn cancel(generator: &mut Generator, state_events: &mut EventWriter<StateEvent>) {
+    if generator.busy() {
+        generator.cancel();
+    } else {
+        state_events.send(StateEvent::PuzzleDialog(None));
+    }
+}
+
+pub fn puzzle_dialog_screen_system(
+    mut cmd: Commands,
+    game_state: Res<GameState>,
+    asset_server: Res<AssetServer>,
+    screen_query: Query<Entity, With<PuzzleDialogScreen>>,
+) {
+    let screen = screen_query.iter().next();
+    match (&game_state.puzzle_dialog, screen) {
+        (Some(_), None) => spawn_puzzle_dialog(&mut cmd, &game_state, &asset_server),
+        (None, Some(screen)) => cmd.entity(screen).despawn_recursive(),
+        _ => {}
+    }
+}
+
+pub fn puzzle_button_system(
+    game_state: Res<GameState>,
+    mut generator: ResMut<Generator>,
+    mut state_events: EventWriter<StateEvent>,
+    mut button_query: Query<
+        (&Interaction, &PuzzleButton, &mut BackgroundColor),
+        Changed<Interaction>,
+    >,
+) {
+    for (interaction, button, mut color) in button_query.iter_mut() {
+        match *interaction {
+            Interaction::Clicked => {
+                if *button == PuzzleButton::Open {
+                    let dialog = PuzzleDialog::new(game_state.puzzle);
+                    state_events.send(StateEvent::PuzzleDialog(Some(dialog)));
+                    continue;
+                }
+                if *button == PuzzleButton::Cancel {
+                    cancel(&mut generator, &mut state_events);
+                    continue;
+                }
+                let mut dialog = match game_state.puzzle_dialog.clone() {
+                    Some(dialog) if !generator.busy() => dialog,
+                    _ => continue,
+                };
+                let settings = &mut dialog.settings;
+                match button {
+                    PuzzleButton::Difficulty => settings.difficulty = settings.difficulty.next(),
+                    PuzzleButton::Variant => settings.layout = settings.layout.next(),
+                    PuzzleButton::Symmetry => settings.symmetry = settings.symmetry.next(),
+                    PuzzleButton::Seed => settings.seed = None,
+                    PuzzleButton::Generate => {
+                        generate(&dialog, &game_state, &mut state_events);
+                        continue;
+                    }
+                    PuzzleButton::Open | PuzzleButton::Cancel => {}
+                }
+                dialog.confirming = false;
+                state_events.send(StateEvent::PuzzleDialog(Some(dialog)));
+            }
+            Interaction::Hovered => {
+                *color = BackgroundColor(game_state.theme.tool.hover);
+            }
+            Interaction::None => {
+                *color = BackgroundColor(game_state.theme.tool.bg);
+            }
+        }
+    }
+}
+
+// Digits typed while the dialog is open make up the seed. Enter generates and
+// Escape works like Cancel.
+pub fn puzzle_dialog_input_system(
+    keyboard: Res<Input<KeyCode>>,
+    mut chars: EventReader<ReceivedCharacter>,
+    game_state: Res<GameState>,
+    mut generator: ResMut<Generator>,
+    mut state_events: EventWriter<StateEvent>,
+) {
+    let digits: Vec<u64> = chars
+        .iter()
+        .filter_map(|e| e.char.to_digit(10))
+        .map(|d| d as u64)
+        .collect();
+
+    if game_state.puzzle_dialog.is_none() {
+        return;
+    }
+    if keyboard.just_pressed(KeyCode::Escape) {
+        cancel(&mut generator, &mut state_events);
+        return;
+    }
+    let mut dialog = match game_state.puzzle_dialog.clone() {
+        Some(dialog) if !generator.busy() => dialog,
+        _ => return,
+    };
+
+    let mut seed = dialog.settings.seed;
+    if keyboard.just_pressed(KeyCode::Back) {
+        seed = seed.map(|s| s / 10).filter(|&s| s > 0);
+    }
+    for d in digits {
+        // Digits that would overflow are dropped
+        seed = Some(match seed {
+            Some(s) => s
+                .checked_mul(10)
+                .and_then(|s| s.checked_add(d))
+                .unwrap_or(s),
+            None => d,
+        });
+    }
+    // Sent ahead of generating, so the new puzzle closes the dialog after
+    if seed != dialog.settings.seed {
+        dialog.settings.seed = seed;
+        dialog.confirming = false;
+        state_events.send(StateEvent::PuzzleDialog(Some(dialog.clone())));
+    }
+
+    if keyboard.just_pressed(KeyCode::Return) {
+        generate(&dialog, &game_state, &mut state_events);
+    }
+}
+
+pub fn puzzle_dialog_text_system(
+    game_state: Res<GameState>,
+    generator: Res<Generator>,
+    button_query: Query<(&PuzzleButton, &Children)>,
+    mut text_query: Query<&mut Text, Without<PuzzleDialogStatus>>,
+    mut status_query: Query<&mut Text, With<PuzzleDialogStatus>>,
+) {
+    let dialog = match &game_state.puzzle_dialog {
+        Some(dialog) => dialog,
+        None => return,
+    };
+    let settings = &dialog.settings;
+    let job = generator.job();
+
+    for (button, children) in button_query.iter() {
+        let value = match button {
+            PuzzleButton::Open => continue,
+            PuzzleButton::Difficulty => format!("Difficulty: {}", settings.difficulty.name()),
+            PuzzleButton::Variant => format!("Variant: {}", settings.layout.name()),
+            PuzzleButton::Symmetry => format!("Symmetry: {}", settings.symmetry.name()),
+            PuzzleButton::Seed => match settings.seed {
+                Some(seed) => format!("Seed: {}", seed),
+                None => "Seed: random".to_string(),
+            },
+            PuzzleButton::Generate if job.is_some() => "Generating...".to_string(),
+            PuzzleButton::Generate if dialog.confirming => "Discard game and generate".to_string(),
+            PuzzleButton::Generate => "Generate".to_string(),
+            PuzzleButton::Cancel if job.is_some() => "Stop".to_string(),
+            PuzzleButton::Cancel => "Cancel".to_string(),
+        };
+        if let Ok(mut text) = text_query.get_mut(children[0]) {
+            if text.sections[0].value != value {
+                text.sections[0].value = value;
+            }
+        }
+    }
+
+    let status = match job {
+        Some(job) => job.describe(),
+        None if dialog.confirming => {
+            "The game in progress will be lost. Click Generate again to start anyway.".to_string()
+        }
+        None => "Type a number to pick the seed, the same seed always gives the same puzzle."
+            .to_string(),
+    };
+    for mut text in status_query.iter_mut() {
+        if text.sections[0].value != status {
+            text.sections[0].value = status.clone();
+        }
+    }
+}
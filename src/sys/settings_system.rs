@@ -0,0 +1,165 @@
+use bevy::prelude::*;
+
+use crate::{
+    evt::StateEvent,
+    rsc::{
+        game_state::GameState,
+        keymap::{KeyChord, Keymap, KEYMAP_PATH},
+    },
+    ui::{spawn_settings_screen, KeymapRow, SettingsButton, SettingsScreen, SettingsStatus},
+};
+
+fn close_settings(keymap: &Keymap, state_events: &mut EventWriter<StateEvent>) {
+    state_events.send(StateEvent::Settings(false));
+    if let Err(e) = keymap.save(KEYMAP_PATH) {
+        eprintln!("Unable to save key bindings: {}", e);
+    }
+}
+
+// Spawns the screen when it's opened and removes it again when it's closed.
+// It's also rebuilt when resetting changes how many bindings there are.
+pub fn settings_screen_system(
+    mut cmd: Commands,
+    game_state: Res<GameState>,
+    keymap: Res<Keymap>,
+    asset_server: Res<AssetServer>,
+    screen_query: Query<Entity, With<SettingsScreen>>,
+    row_query: Query<&KeymapRow>,
+) {
+    let screen = screen_query.iter().next();
+    let stale = row_query.iter().count() != keymap.bindings.len();
+    match (game_state.settings_open, screen) {
+        (true, None) => spawn_settings_screen(&mut cmd, &game_state, &asset_server, &keymap),
+        (true, Some(screen)) if stale => {
+            cmd.entity(screen).despawn_recursive();
+            spawn_settings_screen(&mut cmd, &game_state, &asset_server, &keymap);
+        }
+        (false, Some(screen)) => cmd.entity(screen).despawn_recursive(),
+        _ => {}
+    }
+}
+
+pub fn settings_button_system(
+    game_state: Res<GameState>,
+    mut keymap: ResMut<Keymap>,
+    mut state_events: EventWriter<StateEvent>,
+    mut button_query: Query<
+        (&Interaction, &SettingsButton, &mut BackgroundColor),
+        Changed<Interaction>,
+    >,
+    row_query: Query<(&Interaction, &KeymapRow), Changed<Interaction>>,
+) {
+    for (interaction, button, mut color) in button_query.iter_mut() {
+        match *interaction {
+            Interaction::Clicked => match button {
+                SettingsButton::Open => state_events.send(StateEvent::Settings(true)),
+                SettingsButton::Reset => {
+                    state_events.send(StateEvent::Rebind(None));
+                    *keymap = Keymap::default();
+                }
+                SettingsButton::Close => close_settings(&keymap, &mut state_events),
+            },
+            Interaction::Hovered => {
+                *color = BackgroundColor(game_state.theme.tool.hover);
+            }
+            Interaction::None => {
+                *color = BackgroundColor(game_state.theme.tool.bg);
+            }
+        }
+    }
+
+    for (interaction, row) in row_query.iter() {
+        if *interaction == Interaction::Clicked {
+            state_events.send(StateEvent::Rebind(Some(row.0)));
+        }
+    }
+}
+
+// Takes the next key pressed (with its modifiers) for the binding being
+// changed. Escape cancels that, or closes the screen when nothing is.
+pub fn rebind_system(
+    keyboard: Res<Input<KeyCode>>,
+    game_state: Res<GameState>,
+    mut keymap: ResMut<Keymap>,
+    mut state_events: EventWriter<StateEvent>,
+) {
+    if !game_state.settings_open {
+        return;
+    }
+
+    let key = keyboard
+        .get_just_pressed()
+        .find(|&&key| !KeyChord::is_modifier(key))
+        .cloned();
+    match (game_state.rebinding, key) {
+        (Some(_), Some(KeyCode::Escape)) => {
+            state_events.send(StateEvent::Rebind(None));
+        }
+        (Some(i), Some(key)) => {
+            if let Some(binding) = keymap.bindings.get_mut(i) {
+                binding.0 = KeyChord::pressed(key, &keyboard);
+            }
+            state_events.send(StateEvent::Rebind(None));
+        }
+        (None, Some(KeyCode::Escape)) => close_settings(&keymap, &mut state_events),
+        _ => {}
+    }
+}
+
+pub fn settings_row_system(
+    game_state: Res<GameState>,
+    keymap: Res<Keymap>,
+    mut row_query: Query<(&KeymapRow, &Interaction, &mut BackgroundColor, &Children)>,
+    mut text_query: Query<&mut Text, Without<SettingsStatus>>,
+    mut status_query: Query<&mut Text, With<SettingsStatus>>,
+) {
+    if !game_state.settings_open {
+        return;
+    }
+
+    let conflicts = keymap.conflicts();
+    for (row, interaction, mut color, children) in row_query.iter_mut() {
+        let (chord, binding) = match keymap.bindings.get(row.0) {
+            Some(binding) => binding,
+            None => continue,
+        };
+        let capturing = game_state.rebinding == Some(row.0);
+
+        let bg = if capturing {
+            game_state.theme.tool.selected
+        } else if conflicts.contains(chord) {
+            game_state.theme.grid.invalid
+        } else if *interaction == Interaction::Hovered {
+            game_state.theme.tool.hover
+        } else {
+            game_state.theme.tool.bg
+        };
+        if color.0 != bg {
+            *color = BackgroundColor(bg);
+        }
+
+        let value = if capturing {
+            format!("{}: press a key", binding.label())
+        } else {
+            format!("{}: {}", binding.label(), chord.label())
+        };
+        if let Ok(mut text) = text_query.get_mut(children[0]) {
+            if text.sections[0].value != value {
+                text.sections[0].value = value;
+            }
+        }
+    }
+
+    let status = match conflicts.len() {
+        0 => "Click a binding, then press the keys for it. Escape cancels.".to_string(),
+        n => format!(
+            "{} keys are bound more than once, only the first binding works",
+            n
+        ),
+    };
+    for mut text in status_query.iter_mut() {
+        if text.sections[0].value != status {
+            text.sections[0].value = status.clone();
+        }
+    }
+}
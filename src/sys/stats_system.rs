@@ -0,0 +1,75 @@
+use bevy::prelude::*;
+
+use crate::{
+    evt::{GameOverEvent, GameStartedEvent, StateEvent},
+    rsc::{
+        game_state::GameState,
+        stats::{Stats, STATS_PATH},
+    },
+    ui::{spawn_stats_screen, StatsButton, StatsScreen},
+};
+
+// Saved after every game so nothing's lost if the window's closed mid way
+pub fn stats_system(
+    mut stats: ResMut<Stats>,
+    mut started_events: EventReader<GameStartedEvent>,
+    mut over_events: EventReader<GameOverEvent>,
+) {
+    let mut changed = false;
+    for game in over_events.iter() {
+        stats.finish(game);
+        changed = true;
+    }
+    for GameStartedEvent(settings) in started_events.iter() {
+        stats.start(*settings);
+        changed = true;
+    }
+    if changed {
+        if let Err(e) = stats.save(STATS_PATH) {
+            eprintln!("Unable to save stats: {}", e);
+        }
+    }
+}
+
+pub fn stats_screen_system(
+    mut cmd: Commands,
+    game_state: Res<GameState>,
+    stats: Res<Stats>,
+    asset_server: Res<AssetServer>,
+    screen_query: Query<Entity, With<StatsScreen>>,
+) {
+    let screen = screen_query.iter().next();
+    match (game_state.stats_open, screen) {
+        (true, None) => spawn_stats_screen(&mut cmd, &game_state, &asset_server, &stats),
+        (false, Some(screen)) => cmd.entity(screen).despawn_recursive(),
+        _ => {}
+    }
+}
+
+pub fn stats_button_system(
+    keyboard: Res<Input<KeyCode>>,
+    game_state: Res<GameState>,
+    mut state_events: EventWriter<StateEvent>,
+    mut button_query: Query<
+        (&Interaction, &StatsButton, &mut BackgroundColor),
+        Changed<Interaction>,
+    >,
+) {
+    for (interaction, button, mut color) in button_query.iter_mut() {
+        match *interaction {
+            Interaction::Clicked => {
+                state_events.send(StateEvent::Stats(*button == StatsButton::Open))
+            }
+            Interaction::Hovered => {
+                *color = BackgroundColor(game_state.theme.tool.hover);
+            }
+            Interaction::None => {
+                *color = BackgroundColor(game_state.theme.tool.bg);
+            }
+        }
+    }
+
+    if game_state.stats_open && keyboard.just_pressed(KeyCode::Escape) {
+        state_events.send(StateEvent::Stats(false));
+    }
+}
@@ -3,7 +3,10 @@ use bevy::{
     prelude::*,
 };
 
-use crate::rsc::game_state::{GameState, Tools};
+use crate::{
+    rsc::game_state::{GameState, Tools},
+    ui::MistakeCounter,
+};
 
 // A unit struct to help identify the FPS UI component, since there may be many Text components
 #[derive(Component)]
@@ -15,7 +18,7 @@ pub struct ColorText;
 
 pub fn text_color_system(
     time: Res<Time>,
-    mut game_state: ResMut<GameState>,
+    game_state: Res<GameState>,
     mut query: Query<&mut Text, With<ColorText>>,
 ) {
     for mut text in &mut query {
@@ -27,6 +30,10 @@ pub fn text_color_system(
             Tools::CornerMark => "Corner",
             Tools::Fill => "Fill",
             Tools::Erase => "Erase",
+            Tools::Color => "Color",
+            Tools::Pen => "Pen",
+            Tools::Stamp => "Stamp",
+            Tools::Note => "Note",
             Tools::None => "None",
         };
 
@@ -34,7 +41,7 @@ pub fn text_color_system(
 
         let total = selection
             .iter()
-            .map(|&i| game_state.graph.index(i).map(|gc| gc.value).unwrap_or(0))
+            .map(|&i| game_state.graph.cell(i).map(|gc| gc.value).unwrap_or(0))
             .sum::<u8>();
 
         text.sections[0].value = format!("{}\nS: {}\nP: {}", str, total, game_state.last_cell);
@@ -60,3 +67,19 @@ pub fn text_update_system(
         }
     }
 }
+
+// Shows marks are being checked too, since that's only set from the keyboard
+pub fn mistake_counter_system(
+    game_state: Res<GameState>,
+    mut query: Query<&mut Text, With<MistakeCounter>>,
+) {
+    let mut value = format!("Mistakes: {}", game_state.mistake_count);
+    if game_state.check_marks {
+        value.push_str("\n+ marks");
+    }
+    for mut text in &mut query {
+        if text.sections[0].value != value {
+            text.sections[0].value = value.clone();
+        }
+    }
+}
@@ -0,0 +1,106 @@
+use bevy::prelude::*;
+
+use crate::{
+    evt::{NextThemeEvent, StateEvent, ThemeChangedEvent},
+    rsc::{
+        colors::ThemeColor,
+        game_state::GameState,
+        prefs::{Prefs, PREFS_PATH},
+        themes::Themes,
+    },
+    ui::ThemeButton,
+};
+
+// Seconds between looks at the theme files for changes
+const RELOAD_INTERVAL: f32 = 1.0;
+
+// Moves on to the next theme when asked, and picks up edits to the theme files
+// every so often. Either way the new theme goes to the reducer, which tells
+// whatever was colored by the old one.
+pub fn theme_system(
+    time: Res<Time>,
+    mut themes: ResMut<Themes>,
+    mut prefs: ResMut<Prefs>,
+    mut next_theme_events: EventReader<NextThemeEvent>,
+    mut state_events: EventWriter<StateEvent>,
+    mut since_reload: Local<f32>,
+) {
+    let mut changed = false;
+    for _ in next_theme_events.iter() {
+        themes.next();
+        changed = true;
+    }
+    if changed {
+        prefs.theme = themes.current().name;
+        if let Err(e) = prefs.save(PREFS_PATH) {
+            eprintln!("Unable to save preferences: {}", e);
+        }
+    }
+
+    *since_reload += time.delta_seconds();
+    if *since_reload >= RELOAD_INTERVAL {
+        *since_reload = 0.0;
+        changed |= themes.refresh();
+    }
+
+    if changed {
+        state_events.send(StateEvent::Theme(Box::new(themes.current())));
+    }
+}
+
+// Colors everything that was spawned with one of the theme's colors again.
+// Lists and buttons that are colored by their own systems are left to them.
+pub fn theme_recolor_system(
+    game_state: Res<GameState>,
+    mut theme_changed_event: EventReader<ThemeChangedEvent>,
+    mut query: Query<(&ThemeColor, Option<&mut BackgroundColor>, Option<&mut Text>)>,
+) {
+    if theme_changed_event.iter().count() == 0 {
+        return;
+    }
+    for (theme_color, bg, text) in query.iter_mut() {
+        let color = theme_color.get(&game_state.theme);
+        match (bg, text) {
+            (_, Some(mut text)) => {
+                for section in text.sections.iter_mut() {
+                    section.style.color = color;
+                }
+            }
+            (Some(mut bg), None) => *bg = BackgroundColor(color),
+            (None, None) => {}
+        }
+    }
+}
+
+// Shows the name of the theme in use, clicking it moves on to the next
+pub fn theme_button_system(
+    game_state: Res<GameState>,
+    mut next_theme_event: EventWriter<NextThemeEvent>,
+    mut button_query: Query<
+        (
+            &Interaction,
+            ChangeTrackers<Interaction>,
+            &mut BackgroundColor,
+            &Children,
+        ),
+        With<ThemeButton>,
+    >,
+    mut text_query: Query<&mut Text>,
+) {
+    for (interaction, tracker, mut color, children) in button_query.iter_mut() {
+        if tracker.is_changed() {
+            match *interaction {
+                Interaction::Clicked => next_theme_event.send(NextThemeEvent),
+                Interaction::Hovered => *color = BackgroundColor(game_state.theme.tool.hover),
+                Interaction::None => *color = BackgroundColor(game_state.theme.tool.bg),
+            }
+        }
+
+        let label = format!("Theme: {}", game_state.theme.name);
+        if let Ok(mut text) = text_query.get_mut(children[0]) {
+            if text.sections[0].value != label {
+                text.sections[0].value = label;
+            }
+        }
+    }
+}
@@ -1,16 +1,136 @@
-use bevy::prelude::*;
+use bevy::{prelude::*, ui::FocusPolicy};
 
 use crate::{
-    rsc::game_state::{GameState, Markers, Tools},
+    core::focus::FocusKind,
+    rsc::{
+        colors::ThemeColor,
+        game_state::{Action, GameState, Markers, Tools},
+        keymap::Keymap,
+        stats::Stats,
+        view::BoardView,
+    },
     sys::{
+        clock_system::format_time,
         grid_update_system::{GridCell, GridLabel},
         text::{ColorText, FpsText},
     },
 };
+pub const TOOL_PANEL_WIDTH: f32 = 300.0;
+
 #[derive(Component, Clone, Debug, Default)]
-pub struct Location(pub u8);
+pub struct Location(pub u16);
+#[derive(Component)]
+pub struct Board;
+#[derive(Component)]
+pub struct StampLabel;
+// Nth color painted on a cell
+#[derive(Component)]
+pub struct ColorSegment(pub usize);
+// Font size of a board label at a zoom of 1.0
+#[derive(Component)]
+pub struct FontScale(pub f32);
 #[derive(Component)]
 pub struct ToolButton(pub Tools);
+#[derive(Component)]
+pub struct NoteIndicator;
+#[derive(Component)]
+pub struct NotesPanel;
+#[derive(Component)]
+pub struct NotesText;
+#[derive(Component)]
+pub struct NoteTooltip;
+#[derive(Component)]
+pub struct NoteTooltipText;
+// Panel button that sends an action, greyed out while it wouldn't do anything
+#[derive(Component)]
+pub struct ActionButton(pub Action);
+#[derive(Component)]
+pub struct MistakeCounter;
+#[derive(Component)]
+pub struct HistoryPanel;
+// Entry of the history panel for a node of the history tree
+#[derive(Component)]
+pub struct HistoryRow(pub usize);
+// Pins whatever focus mode of its kind the cursor or selection gives
+#[derive(Component)]
+pub struct PinButton(pub FocusKind);
+#[derive(Component)]
+pub struct FocusList;
+// Entry of the focus panel for the nth pin, clicking it turns the pin on or off
+#[derive(Component)]
+pub struct PinRow(pub usize);
+#[derive(Component)]
+pub struct UnpinButton(pub usize);
+#[derive(Component)]
+pub struct PuzzleDialogScreen;
+#[derive(Component)]
+pub struct PuzzleDialogStatus;
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PuzzleButton {
+    Open,
+    Difficulty,
+    Variant,
+    Symmetry,
+    Seed,
+    Generate,
+    Cancel,
+}
+#[derive(Component)]
+pub struct ClockButton;
+#[derive(Component)]
+pub struct PauseScreen;
+#[derive(Component)]
+pub struct ResumeButton;
+#[derive(Component)]
+pub struct CompletionScreen;
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CompletionButton {
+    NewPuzzle,
+    PlayAgain,
+}
+#[derive(Component)]
+pub struct ProgressPanel;
+#[derive(Component)]
+pub struct ProgressText;
+#[derive(Component)]
+pub struct StatsScreen;
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StatsButton {
+    Open,
+    Close,
+}
+#[derive(Component)]
+pub struct ThemeButton;
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AccessibilityButton {
+    Cues,
+    Smaller,
+    Larger,
+}
+// Side of a cell's outline, shown with cues on for selected cells and the cursor
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CueEdge {
+    Top,
+    Bottom,
+    Left,
+    Right,
+}
+// Icons for states of a filled cell, in the corner its marks would use
+#[derive(Component)]
+pub struct CueIcon;
+#[derive(Component)]
+pub struct SettingsScreen;
+#[derive(Component)]
+pub struct SettingsStatus;
+// Row of the settings screen for the nth binding of the keymap
+#[derive(Component)]
+pub struct KeymapRow(pub usize);
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SettingsButton {
+    Open,
+    Reset,
+    Close,
+}
 
 #[derive(Component)]
 pub struct GridMark(pub Markers);
@@ -22,119 +142,1318 @@ pub struct ToolLabel {
 #[derive(Component)]
 pub struct GridButton;
 
-pub fn debug_panel(asset_server: &Res<AssetServer>) -> (TextBundle, ColorText, Name) {
-    (
-        TextBundle::from_section(
-            "debug",
-            TextStyle {
-                font: asset_server.load("fonts/FiraSans-Bold.ttf"),
-                font_size: 60.0,
-                color: Color::WHITE,
-            },
-        ) // Set the alignment of the Text
-        .with_text_alignment(TextAlignment::TOP_LEFT)
-        .with_style(Style {
-            position_type: PositionType::Absolute,
-            position: UiRect {
-                bottom: bevy::ui::Val::Px(5.0),
-                right: bevy::ui::Val::Px(15.0),
+pub fn debug_panel(asset_server: &Res<AssetServer>) -> (TextBundle, ColorText, Name) {
+    (
+        TextBundle::from_section(
+            "debug",
+            TextStyle {
+                font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                font_size: 60.0,
+                color: Color::WHITE,
+            },
+        ) // Set the alignment of the Text
+        .with_text_alignment(TextAlignment::TOP_LEFT)
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                bottom: bevy::ui::Val::Px(5.0),
+                right: bevy::ui::Val::Px(15.0),
+                ..default()
+            },
+            ..default()
+        }),
+        ColorText,
+        Name::new("Info Panel"),
+    )
+}
+
+pub fn fps(asset_server: &Res<AssetServer>) -> (TextBundle, FpsText, Name) {
+    (
+        TextBundle::from_sections([
+            TextSection::new(
+                "FPS: ",
+                TextStyle {
+                    font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                    font_size: 60.0,
+                    color: Color::WHITE,
+                },
+            ),
+            TextSection::from_style(TextStyle {
+                font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+                font_size: 60.0,
+                color: Color::GOLD,
+            }),
+        ]),
+        FpsText,
+        Name::new("FPS Text"),
+    )
+}
+
+pub fn tool_panel(mut cmd: Commands, game_state: Res<GameState>, asset_server: Res<AssetServer>) {
+    cmd.spawn((
+        NodeBundle {
+            style: Style {
+                size: Size::new(
+                    bevy::ui::Val::Px(TOOL_PANEL_WIDTH),
+                    bevy::ui::Val::Percent(100.0),
+                ),
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    right: bevy::ui::Val::Px(0.0),
+                    top: bevy::ui::Val::Px(0.0),
+                    ..default()
+                },
+                ..default()
+            },
+            background_color: BackgroundColor(game_state.theme.tool.panel_bg),
+            // Above the cells (1) and lines drawn over them (2)
+            z_index: ZIndex::Global(3),
+            ..default()
+        },
+        Name::new("Tool Panel"),
+        ThemeColor::ToolPanelBg,
+    ))
+    .with_children(|parent| {
+        let tools = [
+            (Tools::Fill, "Fill"),
+            (Tools::CornerMark, "Corner"),
+            (Tools::CenterMark, "Center"),
+            (Tools::Erase, "Erase"),
+            (Tools::Color, "Color"),
+            (Tools::Pen, "Pen"),
+            (Tools::Stamp, "Stamp"),
+            (Tools::Note, "Note"),
+        ];
+        for (tool, name) in tools {
+            parent
+                .spawn((
+                    ButtonBundle {
+                        style: Style {
+                            size: Size::new(bevy::ui::Val::Px(100.0), bevy::ui::Val::Px(100.0)),
+                            position_type: PositionType::Absolute,
+                            position: UiRect {
+                                left: bevy::ui::Val::Px(0.0),
+                                top: bevy::ui::Val::Px(110.0 * (tool as i32) as f32),
+                                ..default()
+                            },
+                            align_items: AlignItems::Center,
+                            justify_content: JustifyContent::Center,
+                            ..default()
+                        },
+                        background_color: BackgroundColor(game_state.theme.tool.bg),
+                        ..default()
+                    },
+                    ToolButton(tool),
+                    Name::new("Tool: ".to_string() + name),
+                ))
+                .with_children(|button| {
+                    button.spawn((
+                        TextBundle::from_section(
+                            name,
+                            TextStyle {
+                                font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                                font_size: 40.0,
+                                color: game_state.theme.tool.text,
+                            },
+                        ),
+                        ToolLabel { tool: tool },
+                        ThemeColor::ToolText,
+                    ));
+                });
+        }
+
+        // Under the tool buttons
+        parent
+            .spawn((
+                ButtonBundle {
+                    style: Style {
+                        size: Size::new(bevy::ui::Val::Px(100.0), bevy::ui::Val::Px(80.0)),
+                        position_type: PositionType::Absolute,
+                        position: UiRect {
+                            left: bevy::ui::Val::Px(0.0),
+                            top: bevy::ui::Val::Px(990.0),
+                            ..default()
+                        },
+                        align_items: AlignItems::Center,
+                        justify_content: JustifyContent::Center,
+                        ..default()
+                    },
+                    background_color: BackgroundColor(game_state.theme.tool.bg),
+                    ..default()
+                },
+                ClockButton,
+                Name::new("Clock"),
+                ThemeColor::ToolBg,
+            ))
+            .with_children(|button| {
+                let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+                button.spawn((
+                    TextBundle::from_sections([
+                        TextSection::new(
+                            "",
+                            TextStyle {
+                                font: font.clone(),
+                                font_size: 30.0,
+                                color: game_state.theme.tool.text,
+                            },
+                        ),
+                        TextSection::new(
+                            "",
+                            TextStyle {
+                                font,
+                                font_size: 18.0,
+                                color: game_state.theme.tool.text,
+                            },
+                        ),
+                    ])
+                    .with_text_alignment(TextAlignment::CENTER),
+                    ThemeColor::ToolText,
+                ));
+            });
+
+        // Puzzle notes take up the column beside the tool buttons, clicking
+        // them starts editing
+        parent
+            .spawn((
+                ButtonBundle {
+                    style: Style {
+                        size: Size::new(
+                            bevy::ui::Val::Px(TOOL_PANEL_WIDTH - 120.0),
+                            bevy::ui::Val::Px(200.0),
+                        ),
+                        position_type: PositionType::Absolute,
+                        position: UiRect {
+                            left: bevy::ui::Val::Px(110.0),
+                            top: bevy::ui::Val::Px(0.0),
+                            ..default()
+                        },
+                        flex_direction: FlexDirection::Column,
+                        padding: UiRect::all(bevy::ui::Val::Px(10.0)),
+                        ..default()
+                    },
+                    background_color: BackgroundColor(game_state.theme.tool.bg),
+                    ..default()
+                },
+                NotesPanel,
+                Name::new("Notes Panel"),
+                ThemeColor::ToolBg,
+            ))
+            .with_children(|panel| {
+                panel.spawn((
+                    TextBundle::from_section(
+                        "Notes",
+                        TextStyle {
+                            font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                            font_size: 30.0,
+                            color: game_state.theme.tool.text,
+                        },
+                    ),
+                    ThemeColor::ToolText,
+                ));
+                panel.spawn((
+                    TextBundle::from_section(
+                        "",
+                        TextStyle {
+                            font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                            font_size: 20.0,
+                            color: game_state.theme.tool.text,
+                        },
+                    )
+                    .with_style(Style {
+                        max_size: Size::new(
+                            bevy::ui::Val::Px(TOOL_PANEL_WIDTH - 140.0),
+                            bevy::ui::Val::Undefined,
+                        ),
+                        ..default()
+                    }),
+                    NotesText,
+                    ThemeColor::ToolText,
+                ));
+            });
+
+        // Buttons that pin a focus mode, with the pins listed underneath by
+        // the focus system
+        parent
+            .spawn((
+                NodeBundle {
+                    style: Style {
+                        size: Size::new(
+                            bevy::ui::Val::Px(TOOL_PANEL_WIDTH - 120.0),
+                            bevy::ui::Val::Px(220.0),
+                        ),
+                        position_type: PositionType::Absolute,
+                        position: UiRect {
+                            left: bevy::ui::Val::Px(110.0),
+                            top: bevy::ui::Val::Px(210.0),
+                            ..default()
+                        },
+                        flex_direction: FlexDirection::Column,
+                        padding: UiRect::all(bevy::ui::Val::Px(5.0)),
+                        ..default()
+                    },
+                    background_color: BackgroundColor(game_state.theme.tool.bg),
+                    ..default()
+                },
+                Name::new("Focus Panel"),
+                ThemeColor::ToolBg,
+            ))
+            .with_children(|panel| {
+                panel.spawn((
+                    TextBundle::from_section(
+                        "Focus",
+                        TextStyle {
+                            font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                            font_size: 24.0,
+                            color: game_state.theme.tool.text,
+                        },
+                    ),
+                    ThemeColor::ToolText,
+                ));
+                panel
+                    .spawn(NodeBundle {
+                        style: Style {
+                            size: Size::new(
+                                bevy::ui::Val::Percent(100.0),
+                                bevy::ui::Val::Undefined,
+                            ),
+                            flex_wrap: FlexWrap::Wrap,
+                            ..default()
+                        },
+                        ..default()
+                    })
+                    .with_children(|buttons| {
+                        let kinds = [
+                            FocusKind::Digit,
+                            FocusKind::Row,
+                            FocusKind::Column,
+                            FocusKind::Box,
+                            FocusKind::Peers,
+                        ];
+                        for kind in kinds {
+                            buttons
+                                .spawn((
+                                    ButtonBundle {
+                                        style: Style {
+                                            size: Size::new(
+                                                bevy::ui::Val::Px(52.0),
+                                                bevy::ui::Val::Px(26.0),
+                                            ),
+                                            margin: UiRect::all(bevy::ui::Val::Px(2.0)),
+                                            align_items: AlignItems::Center,
+                                            justify_content: JustifyContent::Center,
+                                            ..default()
+                                        },
+                                        background_color: BackgroundColor(game_state.theme.tool.bg),
+                                        ..default()
+                                    },
+                                    PinButton(kind),
+                                    Name::new("Pin: ".to_string() + kind.name()),
+                                    ThemeColor::ToolBg,
+                                ))
+                                .with_children(|button| {
+                                    button.spawn((
+                                        TextBundle::from_section(
+                                            kind.name(),
+                                            TextStyle {
+                                                font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                                                font_size: 16.0,
+                                                color: game_state.theme.tool.text,
+                                            },
+                                        ),
+                                        ThemeColor::ToolText,
+                                    ));
+                                });
+                        }
+                    });
+                panel.spawn((
+                    NodeBundle {
+                        style: Style {
+                            flex_direction: FlexDirection::Column,
+                            ..default()
+                        },
+                        ..default()
+                    },
+                    FocusList,
+                ));
+            });
+
+        parent
+            .spawn((
+                ButtonBundle {
+                    style: Style {
+                        size: Size::new(bevy::ui::Val::Px(100.0), bevy::ui::Val::Px(100.0)),
+                        position_type: PositionType::Absolute,
+                        position: UiRect {
+                            left: bevy::ui::Val::Px(0.0),
+                            top: bevy::ui::Val::Px(0.0),
+                            ..default()
+                        },
+                        align_items: AlignItems::Center,
+                        justify_content: JustifyContent::Center,
+                        ..default()
+                    },
+                    background_color: BackgroundColor(game_state.theme.tool.bg),
+                    ..default()
+                },
+                PuzzleButton::Open,
+                Name::new("New Puzzle"),
+                ThemeColor::ToolBg,
+            ))
+            .with_children(|button| {
+                button.spawn((
+                    TextBundle::from_section(
+                        "New",
+                        TextStyle {
+                            font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                            font_size: 40.0,
+                            color: game_state.theme.tool.text,
+                        },
+                    ),
+                    ThemeColor::ToolText,
+                ));
+            });
+
+        let actions = [
+            (Action::Undo, "Undo"),
+            (Action::Redo, "Redo"),
+            (Action::Restart, "Restart"),
+            (Action::ClearMarks, "Clear marks"),
+            (Action::Check, "Check"),
+        ];
+        for (i, (action, name)) in actions.into_iter().enumerate() {
+            parent
+                .spawn((
+                    ButtonBundle {
+                        style: Style {
+                            size: Size::new(
+                                bevy::ui::Val::Px((TOOL_PANEL_WIDTH - 130.0) / 2.0),
+                                bevy::ui::Val::Px(50.0),
+                            ),
+                            position_type: PositionType::Absolute,
+                            position: UiRect {
+                                left: bevy::ui::Val::Px(
+                                    110.0 + (i % 2) as f32 * (TOOL_PANEL_WIDTH - 110.0) / 2.0,
+                                ),
+                                top: bevy::ui::Val::Px(440.0 + (i / 2) as f32 * 55.0),
+                                ..default()
+                            },
+                            align_items: AlignItems::Center,
+                            justify_content: JustifyContent::Center,
+                            ..default()
+                        },
+                        background_color: BackgroundColor(game_state.theme.tool.bg),
+                        ..default()
+                    },
+                    ActionButton(action),
+                    Name::new("Action: ".to_string() + name),
+                    ThemeColor::ToolBg,
+                ))
+                .with_children(|button| {
+                    button.spawn((
+                        TextBundle::from_section(
+                            name,
+                            TextStyle {
+                                font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                                font_size: 20.0,
+                                color: game_state.theme.tool.text,
+                            },
+                        ),
+                        ThemeColor::ToolText,
+                    ));
+                });
+        }
+
+        // Beside the check button
+        parent
+            .spawn(NodeBundle {
+                style: Style {
+                    size: Size::new(
+                        bevy::ui::Val::Px((TOOL_PANEL_WIDTH - 130.0) / 2.0),
+                        bevy::ui::Val::Px(50.0),
+                    ),
+                    position_type: PositionType::Absolute,
+                    position: UiRect {
+                        left: bevy::ui::Val::Px(110.0 + (TOOL_PANEL_WIDTH - 110.0) / 2.0),
+                        top: bevy::ui::Val::Px(550.0),
+                        ..default()
+                    },
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                ..default()
+            })
+            .with_children(|node| {
+                node.spawn((
+                    TextBundle::from_section(
+                        "",
+                        TextStyle {
+                            font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                            font_size: 20.0,
+                            color: game_state.theme.tool.text,
+                        },
+                    ),
+                    MistakeCounter,
+                    ThemeColor::ToolText,
+                ));
+            });
+
+        parent
+            .spawn((
+                ButtonBundle {
+                    style: Style {
+                        size: Size::new(
+                            bevy::ui::Val::Px((TOOL_PANEL_WIDTH - 130.0) / 2.0),
+                            bevy::ui::Val::Px(45.0),
+                        ),
+                        position_type: PositionType::Absolute,
+                        position: UiRect {
+                            left: bevy::ui::Val::Px(110.0),
+                            top: bevy::ui::Val::Px(605.0),
+                            ..default()
+                        },
+                        align_items: AlignItems::Center,
+                        justify_content: JustifyContent::Center,
+                        ..default()
+                    },
+                    background_color: BackgroundColor(game_state.theme.tool.bg),
+                    ..default()
+                },
+                SettingsButton::Open,
+                Name::new("Key Bindings"),
+                ThemeColor::ToolBg,
+            ))
+            .with_children(|button| {
+                button.spawn((
+                    TextBundle::from_section(
+                        "Keys",
+                        TextStyle {
+                            font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                            font_size: 30.0,
+                            color: game_state.theme.tool.text,
+                        },
+                    ),
+                    ThemeColor::ToolText,
+                ));
+            });
+
+        parent
+            .spawn((
+                ButtonBundle {
+                    style: Style {
+                        size: Size::new(
+                            bevy::ui::Val::Px((TOOL_PANEL_WIDTH - 130.0) / 2.0),
+                            bevy::ui::Val::Px(45.0),
+                        ),
+                        position_type: PositionType::Absolute,
+                        position: UiRect {
+                            left: bevy::ui::Val::Px(110.0 + (TOOL_PANEL_WIDTH - 110.0) / 2.0),
+                            top: bevy::ui::Val::Px(605.0),
+                            ..default()
+                        },
+                        align_items: AlignItems::Center,
+                        justify_content: JustifyContent::Center,
+                        ..default()
+                    },
+                    background_color: BackgroundColor(game_state.theme.tool.bg),
+                    ..default()
+                },
+                StatsButton::Open,
+                Name::new("Stats"),
+                ThemeColor::ToolBg,
+            ))
+            .with_children(|button| {
+                button.spawn((
+                    TextBundle::from_section(
+                        "Stats",
+                        TextStyle {
+                            font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                            font_size: 30.0,
+                            color: game_state.theme.tool.text,
+                        },
+                    ),
+                    ThemeColor::ToolText,
+                ));
+            });
+
+        // Label is filled in by the theme system
+        parent
+            .spawn((
+                ButtonBundle {
+                    style: Style {
+                        size: Size::new(
+                            bevy::ui::Val::Px(TOOL_PANEL_WIDTH - 120.0),
+                            bevy::ui::Val::Px(40.0),
+                        ),
+                        position_type: PositionType::Absolute,
+                        position: UiRect {
+                            left: bevy::ui::Val::Px(110.0),
+                            top: bevy::ui::Val::Px(660.0),
+                            ..default()
+                        },
+                        align_items: AlignItems::Center,
+                        justify_content: JustifyContent::Center,
+                        ..default()
+                    },
+                    background_color: BackgroundColor(game_state.theme.tool.bg),
+                    ..default()
+                },
+                ThemeButton,
+                Name::new("Theme"),
+                ThemeColor::ToolBg,
+            ))
+            .with_children(|button| {
+                button.spawn((
+                    TextBundle::from_section(
+                        "",
+                        TextStyle {
+                            font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                            font_size: 22.0,
+                            color: game_state.theme.tool.text,
+                        },
+                    ),
+                    ThemeColor::ToolText,
+                ));
+            });
+
+        let buttons = [
+            (AccessibilityButton::Cues, "Cues"),
+            (AccessibilityButton::Smaller, "A-"),
+            (AccessibilityButton::Larger, "A+"),
+        ];
+        for (i, (button, name)) in buttons.into_iter().enumerate() {
+            parent
+                .spawn((
+                    ButtonBundle {
+                        style: Style {
+                            size: Size::new(
+                                bevy::ui::Val::Px((TOOL_PANEL_WIDTH - 130.0) / 3.0),
+                                bevy::ui::Val::Px(30.0),
+                            ),
+                            position_type: PositionType::Absolute,
+                            position: UiRect {
+                                left: bevy::ui::Val::Px(
+                                    110.0 + i as f32 * (TOOL_PANEL_WIDTH - 115.0) / 3.0,
+                                ),
+                                top: bevy::ui::Val::Px(705.0),
+                                ..default()
+                            },
+                            align_items: AlignItems::Center,
+                            justify_content: JustifyContent::Center,
+                            ..default()
+                        },
+                        background_color: BackgroundColor(game_state.theme.tool.bg),
+                        ..default()
+                    },
+                    button,
+                    Name::new("Accessibility: ".to_string() + name),
+                ))
+                .with_children(|button| {
+                    button.spawn((
+                        TextBundle::from_section(
+                            name,
+                            TextStyle {
+                                font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                                font_size: 20.0,
+                                color: game_state.theme.tool.text,
+                            },
+                        ),
+                        ThemeColor::ToolText,
+                    ));
+                });
+        }
+
+        // Undo steps of the current branch, filled in by the history system
+        parent.spawn((
+            NodeBundle {
+                style: Style {
+                    size: Size::new(
+                        bevy::ui::Val::Px(TOOL_PANEL_WIDTH - 120.0),
+                        bevy::ui::Val::Px(330.0),
+                    ),
+                    position_type: PositionType::Absolute,
+                    position: UiRect {
+                        left: bevy::ui::Val::Px(110.0),
+                        top: bevy::ui::Val::Px(740.0),
+                        ..default()
+                    },
+                    flex_direction: FlexDirection::Column,
+                    padding: UiRect::all(bevy::ui::Val::Px(5.0)),
+                    ..default()
+                },
+                background_color: BackgroundColor(game_state.theme.tool.bg),
+                ..default()
+            },
+            HistoryPanel,
+            Name::new("History Panel"),
+            ThemeColor::ToolBg,
+        ));
+    });
+}
+
+// Covers the whole window, one button per binding that waits for a new key
+// when clicked
+pub fn spawn_settings_screen(
+    cmd: &mut Commands,
+    game_state: &GameState,
+    asset_server: &AssetServer,
+    keymap: &Keymap,
+) {
+    let text_style = TextStyle {
+        font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+        font_size: 22.0,
+        color: game_state.theme.tool.text,
+    };
+
+    cmd.spawn((
+        NodeBundle {
+            style: Style {
+                size: Size::new(bevy::ui::Val::Percent(100.0), bevy::ui::Val::Percent(100.0)),
+                position_type: PositionType::Absolute,
+                flex_direction: FlexDirection::Column,
+                padding: UiRect::all(bevy::ui::Val::Px(40.0)),
+                ..default()
+            },
+            background_color: BackgroundColor(game_state.theme.window_bg),
+            // Above everything else, including the tool panel
+            z_index: ZIndex::Global(5),
+            ..default()
+        },
+        SettingsScreen,
+        Name::new("Settings"),
+        ThemeColor::WindowBg,
+    ))
+    .with_children(|parent| {
+        parent.spawn((
+            TextBundle::from_section(
+                "Key bindings",
+                TextStyle {
+                    font_size: 40.0,
+                    ..text_style.clone()
+                },
+            ),
+            ThemeColor::ToolText,
+        ));
+        parent.spawn((
+            TextBundle::from_section("", text_style.clone()),
+            SettingsStatus,
+            ThemeColor::ToolText,
+        ));
+
+        parent
+            .spawn(NodeBundle {
+                style: Style {
+                    size: Size::new(bevy::ui::Val::Percent(100.0), bevy::ui::Val::Px(820.0)),
+                    flex_direction: FlexDirection::Column,
+                    flex_wrap: FlexWrap::Wrap,
+                    align_content: AlignContent::FlexStart,
+                    margin: UiRect::vertical(bevy::ui::Val::Px(20.0)),
+                    ..default()
+                },
+                background_color: Color::NONE.into(),
+                ..default()
+            })
+            .with_children(|rows| {
+                for i in 0..keymap.bindings.len() {
+                    rows.spawn((
+                        ButtonBundle {
+                            style: Style {
+                                size: Size::new(bevy::ui::Val::Px(420.0), bevy::ui::Val::Px(34.0)),
+                                margin: UiRect::all(bevy::ui::Val::Px(3.0)),
+                                padding: UiRect::horizontal(bevy::ui::Val::Px(10.0)),
+                                align_items: AlignItems::Center,
+                                ..default()
+                            },
+                            background_color: BackgroundColor(game_state.theme.tool.bg),
+                            ..default()
+                        },
+                        KeymapRow(i),
+                        ThemeColor::ToolBg,
+                    ))
+                    .with_children(|row| {
+                        row.spawn((
+                            TextBundle::from_section("", text_style.clone()),
+                            ThemeColor::ToolText,
+                        ));
+                    });
+                }
+            });
+
+        parent
+            .spawn(NodeBundle {
+                style: Style {
+                    flex_direction: FlexDirection::Row,
+                    ..default()
+                },
+                background_color: Color::NONE.into(),
+                ..default()
+            })
+            .with_children(|buttons| {
+                for (button, name) in [
+                    (SettingsButton::Reset, "Reset defaults"),
+                    (SettingsButton::Close, "Close"),
+                ] {
+                    buttons
+                        .spawn((
+                            ButtonBundle {
+                                style: Style {
+                                    size: Size::new(
+                                        bevy::ui::Val::Px(200.0),
+                                        bevy::ui::Val::Px(60.0),
+                                    ),
+                                    margin: UiRect::all(bevy::ui::Val::Px(3.0)),
+                                    align_items: AlignItems::Center,
+                                    justify_content: JustifyContent::Center,
+                                    ..default()
+                                },
+                                background_color: BackgroundColor(game_state.theme.tool.bg),
+                                ..default()
+                            },
+                            button,
+                            ThemeColor::ToolBg,
+                        ))
+                        .with_children(|button| {
+                            button.spawn((
+                                TextBundle::from_section(name, text_style.clone()),
+                                ThemeColor::ToolText,
+                            ));
+                        });
+                }
+            });
+    });
+}
+
+// One row for each variant and difficulty played, in the order first played
+pub fn spawn_stats_screen(
+    cmd: &mut Commands,
+    game_state: &GameState,
+    asset_server: &AssetServer,
+    stats: &Stats,
+) {
+    let text_style = TextStyle {
+        font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+        font_size: 22.0,
+        color: game_state.theme.tool.text,
+    };
+    let time = |t: Option<f64>| t.map_or("-".to_string(), format_time);
+    let header = [
+        "Variant",
+        "Difficulty",
+        "Started",
+        "Solved",
+        "Best",
+        "Average",
+        "Streak",
+        "Best streak",
+        "Hints",
+        "Mistakes",
+    ]
+    .map(|s| s.to_string());
+    let mut rows = vec![header];
+    for kind in &stats.kinds {
+        rows.push([
+            kind.layout.name().to_string(),
+            kind.difficulty.name().to_string(),
+            kind.started.to_string(),
+            kind.completed.to_string(),
+            time(kind.best_time),
+            time(kind.average_time()),
+            kind.streak.to_string(),
+            kind.best_streak.to_string(),
+            kind.hints.to_string(),
+            kind.mistakes.to_string(),
+        ]);
+    }
+
+    cmd.spawn((
+        NodeBundle {
+            style: Style {
+                size: Size::new(bevy::ui::Val::Percent(100.0), bevy::ui::Val::Percent(100.0)),
+                position_type: PositionType::Absolute,
+                flex_direction: FlexDirection::Column,
+                padding: UiRect::all(bevy::ui::Val::Px(40.0)),
+                ..default()
+            },
+            background_color: BackgroundColor(game_state.theme.window_bg),
+            z_index: ZIndex::Global(5),
+            ..default()
+        },
+        StatsScreen,
+        Name::new("Stats"),
+        ThemeColor::WindowBg,
+    ))
+    .with_children(|parent| {
+        parent.spawn((
+            TextBundle::from_section(
+                "Stats",
+                TextStyle {
+                    font_size: 40.0,
+                    ..text_style.clone()
+                },
+            ),
+            ThemeColor::ToolText,
+        ));
+        if stats.kinds.is_empty() {
+            parent.spawn((
+                TextBundle::from_section("No games played yet.", text_style.clone()).with_style(
+                    Style {
+                        margin: UiRect::vertical(bevy::ui::Val::Px(20.0)),
+                        ..default()
+                    },
+                ),
+                ThemeColor::ToolText,
+            ));
+        } else {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        margin: UiRect::vertical(bevy::ui::Val::Px(20.0)),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|table| {
+                    // The header row sits on the darker background
+                    for (i, row) in rows.into_iter().enumerate() {
+                        let bg = match i {
+                            0 => ThemeColor::ToolPanelBg,
+                            _ => ThemeColor::ToolBg,
+                        };
+                        table
+                            .spawn((
+                                NodeBundle {
+                                    style: Style {
+                                        margin: UiRect::bottom(bevy::ui::Val::Px(3.0)),
+                                        ..default()
+                                    },
+                                    background_color: BackgroundColor(bg.get(&game_state.theme)),
+                                    ..default()
+                                },
+                                bg,
+                            ))
+                            .with_children(|cells| {
+                                for value in row {
+                                    cells.spawn((
+                                        TextBundle::from_section(value, text_style.clone())
+                                            .with_style(Style {
+                                                size: Size::new(
+                                                    bevy::ui::Val::Px(140.0),
+                                                    bevy::ui::Val::Px(34.0),
+                                                ),
+                                                padding: UiRect::all(bevy::ui::Val::Px(5.0)),
+                                                ..default()
+                                            }),
+                                        ThemeColor::ToolText,
+                                    ));
+                                }
+                            });
+                    }
+                });
+        }
+
+        parent
+            .spawn((
+                ButtonBundle {
+                    style: Style {
+                        size: Size::new(bevy::ui::Val::Px(200.0), bevy::ui::Val::Px(60.0)),
+                        align_items: AlignItems::Center,
+                        justify_content: JustifyContent::Center,
+                        ..default()
+                    },
+                    background_color: BackgroundColor(game_state.theme.tool.bg),
+                    ..default()
+                },
+                StatsButton::Close,
+                ThemeColor::ToolBg,
+            ))
+            .with_children(|button| {
+                button.spawn((
+                    TextBundle::from_section("Close", text_style.clone()),
+                    ThemeColor::ToolText,
+                ));
+            });
+    });
+}
+
+// Dims the window behind a box of choices for the next puzzle. The labels are
+// filled in by the dialog system.
+pub fn spawn_puzzle_dialog(cmd: &mut Commands, game_state: &GameState, asset_server: &AssetServer) {
+    let text_style = TextStyle {
+        font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+        font_size: 28.0,
+        color: game_state.theme.tool.text,
+    };
+    let button_style = Style {
+        size: Size::new(bevy::ui::Val::Px(500.0), bevy::ui::Val::Px(50.0)),
+        margin: UiRect::all(bevy::ui::Val::Px(5.0)),
+        align_items: AlignItems::Center,
+        justify_content: JustifyContent::Center,
+        ..default()
+    };
+
+    cmd.spawn((
+        NodeBundle {
+            style: Style {
+                size: Size::new(bevy::ui::Val::Percent(100.0), bevy::ui::Val::Percent(100.0)),
+                position_type: PositionType::Absolute,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            background_color: Color::rgba(0.0, 0.0, 0.0, 0.6).into(),
+            z_index: ZIndex::Global(5),
+            ..default()
+        },
+        PuzzleDialogScreen,
+        Name::new("New Puzzle Dialog"),
+    ))
+    .with_children(|parent| {
+        parent
+            .spawn((
+                NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        padding: UiRect::all(bevy::ui::Val::Px(30.0)),
+                        ..default()
+                    },
+                    background_color: BackgroundColor(game_state.theme.tool.panel_bg),
+                    ..default()
+                },
+                ThemeColor::ToolPanelBg,
+            ))
+            .with_children(|dialog| {
+                dialog.spawn((
+                    TextBundle::from_section(
+                        "New puzzle",
+                        TextStyle {
+                            font_size: 40.0,
+                            ..text_style.clone()
+                        },
+                    ),
+                    ThemeColor::ToolText,
+                ));
+                for button in [
+                    PuzzleButton::Difficulty,
+                    PuzzleButton::Variant,
+                    PuzzleButton::Symmetry,
+                    PuzzleButton::Seed,
+                    PuzzleButton::Generate,
+                    PuzzleButton::Cancel,
+                ] {
+                    dialog
+                        .spawn((
+                            ButtonBundle {
+                                style: button_style.clone(),
+                                background_color: BackgroundColor(game_state.theme.tool.bg),
+                                ..default()
+                            },
+                            button,
+                            ThemeColor::ToolBg,
+                        ))
+                        .with_children(|button| {
+                            button.spawn((
+                                TextBundle::from_section("", text_style.clone()),
+                                ThemeColor::ToolText,
+                            ));
+                        });
+                }
+                dialog.spawn((
+                    TextBundle::from_section(
+                        "",
+                        TextStyle {
+                            font_size: 22.0,
+                            ..text_style.clone()
+                        },
+                    )
+                    .with_style(Style {
+                        max_size: Size::new(bevy::ui::Val::Px(500.0), bevy::ui::Val::Undefined),
+                        margin: UiRect::top(bevy::ui::Val::Px(10.0)),
+                        ..default()
+                    }),
+                    PuzzleDialogStatus,
+                    ThemeColor::ToolText,
+                ));
+            });
+    });
+}
+
+// Covers the board so it can't be looked at with the clock stopped. The tool
+// panel stays, the clock in it resumes too.
+pub fn spawn_pause_screen(cmd: &mut Commands, game_state: &GameState, asset_server: &AssetServer) {
+    let text_style = TextStyle {
+        font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+        font_size: 28.0,
+        color: game_state.theme.tool.text,
+    };
+
+    cmd.spawn((
+        NodeBundle {
+            style: Style {
+                size: Size::new(bevy::ui::Val::Undefined, bevy::ui::Val::Percent(100.0)),
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    left: bevy::ui::Val::Px(0.0),
+                    right: bevy::ui::Val::Px(TOOL_PANEL_WIDTH),
+                    top: bevy::ui::Val::Px(0.0),
+                    ..default()
+                },
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            background_color: BackgroundColor(game_state.theme.window_bg),
+            // Over the cells and lines, beside the tool panel
+            z_index: ZIndex::Global(3),
+            ..default()
+        },
+        PauseScreen,
+        Name::new("Pause Screen"),
+        ThemeColor::WindowBg,
+    ))
+    .with_children(|screen| {
+        screen.spawn((
+            TextBundle::from_section(
+                "Paused",
+                TextStyle {
+                    font_size: 40.0,
+                    ..text_style.clone()
+                },
+            ),
+            ThemeColor::ToolText,
+        ));
+        screen
+            .spawn((
+                ButtonBundle {
+                    style: Style {
+                        size: Size::new(bevy::ui::Val::Px(300.0), bevy::ui::Val::Px(50.0)),
+                        margin: UiRect::all(bevy::ui::Val::Px(10.0)),
+                        align_items: AlignItems::Center,
+                        justify_content: JustifyContent::Center,
+                        ..default()
+                    },
+                    background_color: BackgroundColor(game_state.theme.tool.bg),
+                    ..default()
+                },
+                ResumeButton,
+                ThemeColor::ToolBg,
+            ))
+            .with_children(|button| {
+                button.spawn((
+                    TextBundle::from_section("Resume", text_style.clone()),
+                    ThemeColor::ToolText,
+                ));
+            });
+    });
+}
+
+// Over everything but the new puzzle dialog, so it can still be opened from
+// the keyboard
+pub fn spawn_completion_screen(
+    cmd: &mut Commands,
+    game_state: &GameState,
+    asset_server: &AssetServer,
+) {
+    let text_style = TextStyle {
+        font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+        font_size: 28.0,
+        color: game_state.theme.tool.text,
+    };
+    let puzzle = &game_state.puzzle;
+    let stats = [
+        format!("Time: {}", format_time(game_state.elapsed)),
+        format!(
+            "Difficulty: {} {}",
+            puzzle.difficulty.name(),
+            puzzle.layout.name()
+        ),
+        format!("Mistakes: {}", game_state.mistake_count),
+        format!("Hints: {}", game_state.hints),
+    ];
+
+    cmd.spawn((
+        NodeBundle {
+            style: Style {
+                size: Size::new(bevy::ui::Val::Percent(100.0), bevy::ui::Val::Percent(100.0)),
+                position_type: PositionType::Absolute,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
                 ..default()
             },
+            background_color: Color::rgba(0.0, 0.0, 0.0, 0.6).into(),
+            z_index: ZIndex::Global(4),
             ..default()
-        }),
-        ColorText,
-        Name::new("Info Panel"),
-    )
+        },
+        CompletionScreen,
+        Name::new("Completion Screen"),
+    ))
+    .with_children(|parent| {
+        parent
+            .spawn((
+                NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        padding: UiRect::all(bevy::ui::Val::Px(30.0)),
+                        ..default()
+                    },
+                    background_color: BackgroundColor(game_state.theme.tool.panel_bg),
+                    ..default()
+                },
+                ThemeColor::ToolPanelBg,
+            ))
+            .with_children(|dialog| {
+                dialog.spawn((
+                    TextBundle::from_section(
+                        "Solved!",
+                        TextStyle {
+                            font_size: 40.0,
+                            ..text_style.clone()
+                        },
+                    ),
+                    ThemeColor::ToolText,
+                ));
+                for line in stats {
+                    dialog.spawn((
+                        TextBundle::from_section(line, text_style.clone()).with_style(Style {
+                            margin: UiRect::all(bevy::ui::Val::Px(5.0)),
+                            ..default()
+                        }),
+                        ThemeColor::ToolText,
+                    ));
+                }
+                for button in [CompletionButton::NewPuzzle, CompletionButton::PlayAgain] {
+                    dialog
+                        .spawn((
+                            ButtonBundle {
+                                style: Style {
+                                    size: Size::new(
+                                        bevy::ui::Val::Px(500.0),
+                                        bevy::ui::Val::Px(50.0),
+                                    ),
+                                    margin: UiRect::all(bevy::ui::Val::Px(5.0)),
+                                    align_items: AlignItems::Center,
+                                    justify_content: JustifyContent::Center,
+                                    ..default()
+                                },
+                                background_color: BackgroundColor(game_state.theme.tool.bg),
+                                ..default()
+                            },
+                            button,
+                            ThemeColor::ToolBg,
+                        ))
+                        .with_children(|button| {
+                            button.spawn((
+                                TextBundle::from_section("", text_style.clone()),
+                                ThemeColor::ToolText,
+                            ));
+                        });
+                }
+            });
+    });
 }
 
-pub fn fps(asset_server: &Res<AssetServer>) -> (TextBundle, FpsText, Name) {
-    (
-        TextBundle::from_sections([
-            TextSection::new(
-                "FPS: ",
+// Shows the note of the hovered cell, or the one being edited, beside it
+pub fn note_tooltip(mut cmd: Commands, game_state: Res<GameState>, asset_server: Res<AssetServer>) {
+    cmd.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                padding: UiRect::all(bevy::ui::Val::Px(8.0)),
+                display: Display::None,
+                ..default()
+            },
+            background_color: BackgroundColor(game_state.theme.tool.panel_bg),
+            focus_policy: FocusPolicy::Pass,
+            // Over the cells and lines, level with the tool panel
+            z_index: ZIndex::Global(3),
+            ..default()
+        },
+        NoteTooltip,
+        Name::new("Note Tooltip"),
+        ThemeColor::ToolPanelBg,
+    ))
+    .with_children(|parent| {
+        parent.spawn((
+            TextBundle::from_section(
+                "",
                 TextStyle {
                     font: asset_server.load("fonts/FiraSans-Bold.ttf"),
-                    font_size: 60.0,
-                    color: Color::WHITE,
+                    font_size: 24.0,
+                    color: game_state.theme.tool.text,
                 },
-            ),
-            TextSection::from_style(TextStyle {
-                font: asset_server.load("fonts/FiraMono-Medium.ttf"),
-                font_size: 60.0,
-                color: Color::GOLD,
+            )
+            .with_style(Style {
+                max_size: Size::new(bevy::ui::Val::Px(300.0), bevy::ui::Val::Undefined),
+                ..default()
             }),
-        ]),
-        FpsText,
-        Name::new("FPS Text"),
-    )
+            NoteTooltipText,
+            ThemeColor::ToolText,
+        ));
+    });
 }
 
-pub fn tool_panel(mut cmd: Commands, game_state: Res<GameState>, asset_server: Res<AssetServer>) {
+// Says what's being worked on in the background, hidden the rest of the time
+pub fn progress_panel(
+    mut cmd: Commands,
+    game_state: Res<GameState>,
+    asset_server: Res<AssetServer>,
+) {
     cmd.spawn((
         NodeBundle {
             style: Style {
-                size: Size::new(bevy::ui::Val::Px(300.0), bevy::ui::Val::Percent(100.0)),
                 position_type: PositionType::Absolute,
                 position: UiRect {
-                    right: bevy::ui::Val::Px(0.0),
-                    top: bevy::ui::Val::Px(0.0),
+                    left: bevy::ui::Val::Px(20.0),
+                    bottom: bevy::ui::Val::Px(20.0),
                     ..default()
                 },
+                padding: UiRect::all(bevy::ui::Val::Px(10.0)),
+                display: Display::None,
                 ..default()
             },
             background_color: BackgroundColor(game_state.theme.tool.panel_bg),
-            z_index: ZIndex::Global(1),
+            focus_policy: FocusPolicy::Pass,
+            z_index: ZIndex::Global(3),
             ..default()
         },
-        Name::new("Tool Panel"),
+        ProgressPanel,
+        Name::new("Progress"),
+        ThemeColor::ToolPanelBg,
     ))
     .with_children(|parent| {
-        let tools = [
-            (Tools::Fill, "Fill"),
-            (Tools::CornerMark, "Corner"),
-            (Tools::CenterMark, "Center"),
-            (Tools::Erase, "Erase"),
-        ];
-        for (tool, name) in tools {
-            parent
-                .spawn((
-                    ButtonBundle {
-                        style: Style {
-                            size: Size::new(bevy::ui::Val::Px(100.0), bevy::ui::Val::Px(100.0)),
-                            position_type: PositionType::Absolute,
-                            position: UiRect {
-                                left: bevy::ui::Val::Px(0.0),
-                                top: bevy::ui::Val::Px(110.0 * (tool as i32) as f32),
-                                ..default()
-                            },
-                            align_items: AlignItems::Center,
-                            justify_content: JustifyContent::Center,
-                            ..default()
-                        },
-                        background_color: BackgroundColor(game_state.theme.tool.bg),
-                        ..default()
-                    },
-                    ToolButton(tool),
-                    Name::new("Tool: ".to_string() + name),
-                ))
-                .with_children(|button| {
-                    button.spawn((
-                        TextBundle::from_section(
-                            name,
-                            TextStyle {
-                                font: asset_server.load("fonts/FiraSans-Bold.ttf"),
-                                font_size: 40.0,
-                                color: game_state.theme.tool.text,
-                            },
-                        ),
-                        ToolLabel { tool: tool },
-                    ));
-                });
-        }
+        parent.spawn((
+            TextBundle::from_section(
+                "",
+                TextStyle {
+                    font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                    font_size: 24.0,
+                    color: game_state.theme.tool.text,
+                },
+            ),
+            ProgressText,
+            ThemeColor::ToolText,
+        ));
     });
 }
 
-pub fn board(mut cmd: Commands, game_state: Res<GameState>, asset_server: Res<AssetServer>) {
+pub fn board(
+    mut cmd: Commands,
+    game_state: Res<GameState>,
+    asset_server: Res<AssetServer>,
+    view: Res<BoardView>,
+) {
+    spawn_board(&mut cmd, &game_state, &asset_server, &view);
+}
+
+pub fn spawn_board(
+    cmd: &mut Commands,
+    game_state: &GameState,
+    asset_server: &AssetServer,
+    view: &BoardView,
+) {
     cmd.spawn((
         NodeBundle {
             style: Style {
@@ -146,45 +1465,40 @@ pub fn board(mut cmd: Commands, game_state: Res<GameState>, asset_server: Res<As
             background_color: BackgroundColor(game_state.theme.window_bg),
             ..default()
         },
+        Board,
         Name::new("Grid"),
+        ThemeColor::WindowBg,
     ))
     .with_children(|parent| {
-        let mut i = 1;
-        let l = 100; // Left Margin
-        let t = 10; // Top Margin
-        let w = 100; // Width
-        let h = 100; // Height
-        let y = 9; // Row Length
-        while i <= 81 {
+        for cell in &game_state.graph.cells {
             spawn_cell(
                 parent,
-                i,
-                l + (w * (1 + ((i - 1) % y))),
-                t + (h * (0 + ceil(i, y))),
-                &game_state,
-                &asset_server,
+                game_state.graph.point_to_index(cell.x, cell.y),
+                cell.x,
+                cell.y,
+                game_state,
+                asset_server,
+                view,
             );
-            i += 1;
         }
     });
 }
 
-fn ceil(x: i32, y: i32) -> i32 {
-    (x + y - 1) / y
-}
-
 fn spawn_cell(
     parent: &mut ChildBuilder,
-    i: i32,
-    x: i32,
-    y: i32,
-    game_state: &Res<GameState>,
-    asset_server: &Res<AssetServer>,
+    i: u16,
+    x: u8,
+    y: u8,
+    game_state: &GameState,
+    asset_server: &AssetServer,
+    view: &BoardView,
 ) -> Entity {
-    let show_right = (i % 3) == 0;
-    let show_top = i > 0 && i < 10 || i > 27 && i < 37 || i > 54 && i < 64;
-    let show_bottom = i > 18 && i < 28 || i > 45 && i < 55 || i > 72 && i < 82;
-    let show_left = ((i % 3) + 1) - 2 == 0;
+    // Every layout places its grids on box boundaries, so box edges can be
+    // found from the board position alone.
+    let show_right = x.is_multiple_of(3);
+    let show_top = (y - 1).is_multiple_of(3);
+    let show_bottom = y.is_multiple_of(3);
+    let show_left = (x - 1).is_multiple_of(3);
 
     let border_width = 5.0;
     let border_mod = 1.0;
@@ -193,24 +1507,26 @@ fn spawn_cell(
         left: bevy::ui::Val::Px(if show_left { border_width } else { border_mod }),
         top: bevy::ui::Val::Px(if show_top { border_width } else { border_mod }),
         right: bevy::ui::Val::Px(if show_right { border_width } else { border_mod }),
-        bottom: bevy::ui::Val::Px(if (show_bottom) && i != 1 {
+        bottom: bevy::ui::Val::Px(if show_bottom {
             border_width
         } else {
             border_mod
         }),
     };
 
+    let size = view.cell_size();
+    let position = view.cell_position(x, y);
     let mut cmds = parent.spawn((
         NodeBundle {
             style: Style {
-                size: Size::new(bevy::ui::Val::Px(100.0), bevy::ui::Val::Px(100.0)),
-                min_size: Size::new(bevy::ui::Val::Px(100.0), bevy::ui::Val::Px(100.0)),
+                size: Size::new(bevy::ui::Val::Px(size), bevy::ui::Val::Px(size)),
+                min_size: Size::new(bevy::ui::Val::Px(size), bevy::ui::Val::Px(size)),
                 margin: UiRect::all(Val::Px(0.0)),
                 padding: UiRect::all(Val::Px(0.0)),
                 position_type: PositionType::Absolute,
                 position: UiRect {
-                    left: bevy::ui::Val::Px(x as f32),
-                    top: bevy::ui::Val::Px(y as f32),
+                    left: bevy::ui::Val::Px(position.x),
+                    top: bevy::ui::Val::Px(position.y),
                     ..default()
                 },
                 border: rect,
@@ -220,13 +1536,14 @@ fn spawn_cell(
             ..default()
         },
         GridCell {
-            index: i as u8,
-            x: (i % 9) as u8,
-            y: (i / 9) as u8,
+            index: i,
+            x,
+            y,
             value: 0,
             ..Default::default()
         },
         Name::new(i.to_string()),
+        ThemeColor::GridBorder,
     ));
     cmds.with_children(|parent| {
         parent
@@ -250,16 +1567,95 @@ fn spawn_cell(
                 Name::new(i.to_string()),
             ))
             .with_children(|parent| {
+                // Spawned first so digits and marks draw over the paint. Unused
+                // segments are hidden and the rest grow to split the cell evenly.
+                parent
+                    .spawn((
+                        NodeBundle {
+                            style: Style {
+                                size: Size::new(
+                                    bevy::ui::Val::Percent(100.0),
+                                    bevy::ui::Val::Percent(100.0),
+                                ),
+                                position_type: PositionType::Absolute,
+                                flex_direction: FlexDirection::Row,
+                                ..default()
+                            },
+                            background_color: Color::NONE.into(),
+                            focus_policy: FocusPolicy::Pass,
+                            ..default()
+                        },
+                        Name::new("Colors"),
+                    ))
+                    .with_children(|parent| {
+                        for segment in 0..9 {
+                            parent.spawn((
+                                NodeBundle {
+                                    style: Style {
+                                        size: Size::new(
+                                            bevy::ui::Val::Auto,
+                                            bevy::ui::Val::Percent(100.0),
+                                        ),
+                                        flex_grow: 1.0,
+                                        display: Display::None,
+                                        ..default()
+                                    },
+                                    background_color: Color::NONE.into(),
+                                    focus_policy: FocusPolicy::Pass,
+                                    ..default()
+                                },
+                                ColorSegment(segment),
+                                Location(i),
+                            ));
+                        }
+                    });
+                // Fira lacks the stars, DejaVu has every shape glyph
+                parent
+                    .spawn((
+                        NodeBundle {
+                            style: Style {
+                                size: Size::new(
+                                    bevy::ui::Val::Percent(100.0),
+                                    bevy::ui::Val::Percent(100.0),
+                                ),
+                                position_type: PositionType::Absolute,
+                                align_items: AlignItems::Center,
+                                justify_content: JustifyContent::Center,
+                                ..default()
+                            },
+                            background_color: Color::NONE.into(),
+                            focus_policy: FocusPolicy::Pass,
+                            ..default()
+                        },
+                        Name::new("Stamps"),
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn((
+                            TextBundle::from_section(
+                                "",
+                                TextStyle {
+                                    font: asset_server.load("fonts/DejaVuSansMono.ttf"),
+                                    font_size: view.font_size(60.0),
+                                    color: game_state.theme.grid.text,
+                                },
+                            ),
+                            StampLabel,
+                            Location(i),
+                            FontScale(60.0),
+                        ));
+                    });
                 parent.spawn((
                     TextBundle::from_section(
                         i.to_string(),
                         TextStyle {
                             font: asset_server.load("fonts/FiraSans-Bold.ttf"),
-                            font_size: 60.0,
+                            font_size: view.font_size(60.0),
                             color: game_state.theme.grid.text,
                         },
                     ),
                     GridLabel,
+                    FontScale(60.0),
+                    ThemeColor::GridText,
                 ));
                 let corners = [
                     (
@@ -298,6 +1694,51 @@ fn spawn_cell(
                             ..default()
                         },
                     ),
+                    (
+                        "T",
+                        GridMark(Markers::T),
+                        UiRect {
+                            top: bevy::ui::Val::Px(0.0),
+                            left: bevy::ui::Val::Percent(33.5),
+                            ..default()
+                        },
+                    ),
+                    (
+                        "B",
+                        GridMark(Markers::B),
+                        UiRect {
+                            bottom: bevy::ui::Val::Px(0.0),
+                            left: bevy::ui::Val::Percent(33.5),
+                            ..default()
+                        },
+                    ),
+                    (
+                        "L",
+                        GridMark(Markers::L),
+                        UiRect {
+                            top: bevy::ui::Val::Percent(33.5),
+                            left: bevy::ui::Val::Px(0.0),
+                            ..default()
+                        },
+                    ),
+                    (
+                        "R",
+                        GridMark(Markers::R),
+                        UiRect {
+                            top: bevy::ui::Val::Percent(33.5),
+                            right: bevy::ui::Val::Px(0.0),
+                            ..default()
+                        },
+                    ),
+                    (
+                        "M",
+                        GridMark(Markers::M),
+                        UiRect {
+                            top: bevy::ui::Val::Percent(33.5),
+                            left: bevy::ui::Val::Percent(33.5),
+                            ..default()
+                        },
+                    ),
                 ];
                 for corner in corners {
                     parent
@@ -305,8 +1746,8 @@ fn spawn_cell(
                             NodeBundle {
                                 style: Style {
                                     size: Size::new(
-                                        bevy::ui::Val::Px(33.0),
-                                        bevy::ui::Val::Px(33.0),
+                                        bevy::ui::Val::Percent(33.0),
+                                        bevy::ui::Val::Percent(33.0),
                                     ),
                                     margin: UiRect::all(Val::Px(0.0)),
                                     padding: UiRect::all(Val::Px(0.0)),
@@ -316,11 +1757,12 @@ fn spawn_cell(
                                     justify_content: JustifyContent::SpaceAround,
                                     ..default()
                                 },
-                                background_color: game_state.theme.grid.hover.into(),
+                                background_color: Color::NONE.into(),
+                                focus_policy: FocusPolicy::Pass,
                                 ..default()
                             },
                             corner.1,
-                            Location(i as u8),
+                            Location(i),
                             Name::new(corner.0),
                         ))
                         .with_children(|parent| {
@@ -329,13 +1771,15 @@ fn spawn_cell(
                                     "",
                                     TextStyle {
                                         font: asset_server.load("fonts/FiraSans-Bold.ttf"),
-                                        font_size: 30.0,
+                                        font_size: view.font_size(30.0),
                                         color: game_state.theme.grid.text,
                                     },
                                 ),
-                                Location(i as u8),
+                                Location(i),
                                 GridLabel,
+                                FontScale(30.0),
                                 Name::new(corner.0),
+                                ThemeColor::GridText,
                             ));
                         });
                 }
@@ -343,24 +1787,28 @@ fn spawn_cell(
                     .spawn((
                         NodeBundle {
                             style: Style {
-                                size: Size::new(bevy::ui::Val::Px(93.0), bevy::ui::Val::Px(33.3)),
+                                size: Size::new(
+                                    bevy::ui::Val::Percent(93.0),
+                                    bevy::ui::Val::Percent(33.3),
+                                ),
                                 margin: UiRect::all(Val::Px(0.0)),
                                 padding: UiRect::all(Val::Px(0.0)),
                                 position_type: PositionType::Absolute,
                                 position: UiRect {
                                     left: bevy::ui::Val::Px(0.0),
-                                    bottom: bevy::ui::Val::Px(30.0),
+                                    bottom: bevy::ui::Val::Percent(30.0),
                                     ..default()
                                 },
                                 align_items: AlignItems::Center,
                                 justify_content: JustifyContent::SpaceAround,
                                 ..default()
                             },
-                            background_color: game_state.theme.grid.invalid.into(),
+                            background_color: Color::NONE.into(),
+                            focus_policy: FocusPolicy::Pass,
                             ..default()
                         },
                         GridMark(Markers::Center),
-                        Location(i as u8),
+                        Location(i),
                         Name::new("Center Mark"),
                     ))
                     .with_children(|parent| {
@@ -369,15 +1817,100 @@ fn spawn_cell(
                                 "",
                                 TextStyle {
                                     font: asset_server.load("fonts/FiraSans-Bold.ttf"),
-                                    font_size: 30.0,
+                                    font_size: view.font_size(30.0),
                                     color: game_state.theme.grid.text,
                                 },
                             ),
-                            Location(i as u8),
+                            Location(i),
                             GridLabel,
+                            FontScale(30.0),
                             Name::new("Center"),
+                            ThemeColor::GridText,
                         ));
                     });
+                parent.spawn((
+                    NodeBundle {
+                        style: Style {
+                            size: Size::new(
+                                bevy::ui::Val::Percent(12.0),
+                                bevy::ui::Val::Percent(12.0),
+                            ),
+                            position_type: PositionType::Absolute,
+                            position: UiRect {
+                                top: bevy::ui::Val::Px(0.0),
+                                right: bevy::ui::Val::Px(0.0),
+                                ..default()
+                            },
+                            display: Display::None,
+                            ..default()
+                        },
+                        background_color: game_state.theme.grid.note.into(),
+                        focus_policy: FocusPolicy::Pass,
+                        ..default()
+                    },
+                    NoteIndicator,
+                    Location(i),
+                    Name::new("Note"),
+                    ThemeColor::GridNote,
+                ));
+                // Last so they're drawn over everything else in the cell,
+                // hidden until the cue system shows them
+                for edge in [CueEdge::Top, CueEdge::Bottom, CueEdge::Left, CueEdge::Right] {
+                    let position = match edge {
+                        CueEdge::Bottom => UiRect {
+                            bottom: bevy::ui::Val::Px(0.0),
+                            left: bevy::ui::Val::Px(0.0),
+                            ..default()
+                        },
+                        CueEdge::Right => UiRect {
+                            top: bevy::ui::Val::Px(0.0),
+                            right: bevy::ui::Val::Px(0.0),
+                            ..default()
+                        },
+                        CueEdge::Top | CueEdge::Left => UiRect {
+                            top: bevy::ui::Val::Px(0.0),
+                            left: bevy::ui::Val::Px(0.0),
+                            ..default()
+                        },
+                    };
+                    parent.spawn((
+                        NodeBundle {
+                            style: Style {
+                                position_type: PositionType::Absolute,
+                                position,
+                                display: Display::None,
+                                ..default()
+                            },
+                            focus_policy: FocusPolicy::Pass,
+                            ..default()
+                        },
+                        edge,
+                        Location(i),
+                    ));
+                }
+                parent.spawn((
+                    TextBundle::from_section(
+                        "",
+                        TextStyle {
+                            font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                            font_size: view.font_size(30.0),
+                            color: game_state.theme.grid.text,
+                        },
+                    )
+                    .with_style(Style {
+                        position_type: PositionType::Absolute,
+                        position: UiRect {
+                            top: bevy::ui::Val::Px(0.0),
+                            left: bevy::ui::Val::Px(4.0),
+                            ..default()
+                        },
+                        ..default()
+                    }),
+                    CueIcon,
+                    Location(i),
+                    FontScale(30.0),
+                    Name::new("Cues"),
+                ));
             });
     });
     return cmds.id();